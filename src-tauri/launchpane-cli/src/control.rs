@@ -0,0 +1,104 @@
+// Client for the control socket of a running Launchpane app, see `src/control.rs` in the app
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+#[cfg(target_family = "unix")]
+use serde_json::json;
+#[cfg(target_family = "unix")]
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+        UnixStream,
+    },
+};
+
+pub const CONTROL_SOCKET_FILE_NAME: &str = "launchpane.sock";
+
+#[cfg(target_family = "unix")]
+pub struct ControlClient {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+#[cfg(target_family = "unix")]
+impl ControlClient {
+    // None if the app isn't running
+    pub async fn connect(data_dir: &Path) -> Option<Self> {
+        let stream = UnixStream::connect(data_dir.join(CONTROL_SOCKET_FILE_NAME))
+            .await
+            .ok()?;
+
+        let (reader, writer) = stream.into_split();
+
+        Some(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+        })
+    }
+
+    pub async fn call<T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, String> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut request = serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }))
+        .map_err(|e| e.to_string())?;
+        request.push(b'\n');
+
+        self.writer
+            .write_all(&request)
+            .await
+            .map_err(|e| format!("Failed to send request: {}", e))?;
+
+        let line = self
+            .lines
+            .next_line()
+            .await
+            .map_err(|e| format!("Failed to read response: {}", e))?
+            .ok_or("Launchpane closed the connection")?;
+
+        let mut response: Value = serde_json::from_str(&line).map_err(|e| e.to_string())?;
+
+        if let Some(error) = response.get("error") {
+            return Err(match error.get("data") {
+                Some(data) => data.to_string(),
+                None => error["message"].as_str().unwrap_or_default().into(),
+            });
+        }
+
+        serde_json::from_value(response["result"].take()).map_err(|e| e.to_string())
+    }
+}
+
+// There's no control socket on windows, so the CLI always works on the database directly
+#[cfg(target_family = "windows")]
+pub struct ControlClient;
+
+#[cfg(target_family = "windows")]
+impl ControlClient {
+    pub async fn connect(_data_dir: &Path) -> Option<Self> {
+        None
+    }
+
+    pub async fn call<T: DeserializeOwned>(
+        &mut self,
+        _method: &str,
+        _params: Value,
+    ) -> Result<T, String> {
+        unreachable!("No control client can be created on windows")
+    }
+}
@@ -0,0 +1,141 @@
+// Running commands without the app, in the foreground of the terminal
+
+use std::time::Instant;
+
+use async_process::Stdio;
+use futures_lite::{io::BufReader, AsyncBufReadExt, StreamExt};
+use tokio::{sync::Mutex, try_join};
+
+use crate::{
+    prisma::{command, command_log_line, PrismaClient},
+    run_clock::{load_latest_run, RunClock},
+    shell::build_command,
+};
+
+// Same values as the app uses
+pub const STDOUT: i32 = 1;
+pub const STDERR: i32 = 2;
+pub const INFO: i32 = 3;
+
+// Orders the lines of the run and measures their time like the app does, as a new run after the
+// latest stored one
+async fn start_run(client: &PrismaClient, command_id: i32) -> Result<Mutex<RunClock>, String> {
+    let (latest_run, _) = load_latest_run(client, command_id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    RunClock::new(latest_run + 1, 0)
+        .map(Mutex::new)
+        .map_err(|e| e.to_string())
+}
+
+// The clock is held until the line is stored, so the ids of the lines are in their order
+async fn add_log_line(
+    client: &PrismaClient,
+    clock: &Mutex<RunClock>,
+    command_id: i32,
+    source: i32,
+    line: String,
+) -> Result<(), String> {
+    let mut clock = clock.lock().await;
+    let stamp = clock.tick_at(Instant::now());
+
+    client
+        .command_log_line()
+        .create(
+            command::id::equals(command_id),
+            source,
+            line,
+            stamp.timestamp,
+            vec![
+                command_log_line::run::set(stamp.run),
+                command_log_line::sequence::set(stamp.sequence),
+            ],
+        )
+        .exec()
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+// Runs the command until it exits, printing its output and storing it like the app would.
+// Returns the exit code.
+pub async fn run_in_foreground(
+    client: &PrismaClient,
+    command: &command::Data,
+) -> Result<i32, String> {
+    let clock = start_run(client, command.id).await?;
+    let mut cmd = build_command(command, None)?;
+
+    cmd.current_dir(&command.cwd)
+        .env("INSTANCE_INDEX", "0")
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Command failed to start: {}", e))?;
+
+    add_log_line(
+        client,
+        &clock,
+        command.id,
+        INFO,
+        format!("Running command `{}` at `{}`", command.command, command.cwd),
+    )
+    .await?;
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    let out_process = async {
+        let mut lines = BufReader::new(stdout).lines();
+
+        while let Some(line) = lines.try_next().await.map_err(|e| e.to_string())? {
+            println!("{}", line);
+            add_log_line(client, &clock, command.id, STDOUT, line).await?;
+        }
+
+        Ok::<(), String>(())
+    };
+
+    let err_process = async {
+        let mut lines = BufReader::new(stderr).lines();
+
+        while let Some(line) = lines.try_next().await.map_err(|e| e.to_string())? {
+            eprintln!("{}", line);
+            add_log_line(client, &clock, command.id, STDERR, line).await?;
+        }
+
+        Ok::<(), String>(())
+    };
+
+    try_join!(out_process, err_process)?;
+
+    let status = child.status().await.map_err(|e| e.to_string())?;
+
+    add_log_line(
+        client,
+        &clock,
+        command.id,
+        INFO,
+        format!("Command finished with {}", status),
+    )
+    .await?;
+
+    client
+        .command()
+        .update(
+            command::id::equals(command.id),
+            vec![
+                command::last_run_result_type::set(Some("exit".into())),
+                command::last_run_code::set(status.code().map(|c| c.to_string())),
+            ],
+        )
+        .exec()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(status.code().unwrap_or(1))
+}
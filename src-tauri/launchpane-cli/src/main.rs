@@ -0,0 +1,401 @@
+// Command line companion for Launchpane. Reads the app's database directly, and goes through the
+// app's control socket for anything that touches running processes, so the app stays their owner.
+
+// Generated by `cargo prisma generate` in the app
+#[allow(warnings, unused)]
+#[path = "../../src/prisma.rs"]
+mod prisma;
+
+#[allow(dead_code)]
+#[path = "../../src/shell.rs"]
+mod shell;
+
+#[path = "../../src/run_clock.rs"]
+mod run_clock;
+
+mod control;
+mod direct;
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use control::ControlClient;
+use directories::ProjectDirs;
+use prisma::{command, command_log_line, PrismaClient};
+use prisma_client_rust::Direction;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::time::{sleep, Duration};
+
+const USAGE: &str = "Usage:
+    launchpane list
+    launchpane run <name>
+    launchpane stop <name>
+    launchpane status
+    launchpane logs <name> [--follow] [--lines <count>]
+
+Commands can be referred to by name or by id.
+Set LAUNCHPANE_DATA_DIR to use a different data directory.";
+
+const DEFAULT_LOG_LINES: i64 = 100;
+
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+// Lines printed per query when following, a command can log faster than we poll
+const FOLLOW_PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug, PartialEq)]
+enum CliCommand {
+    List,
+    Run(String),
+    Stop(String),
+    Status,
+    Logs {
+        name: String,
+        follow: bool,
+        lines: i64,
+    },
+}
+
+// Mirrors ProcessStatus in the app
+#[derive(Deserialize)]
+enum ProcessStatus {
+    Running { instances: u32 },
+    Stopping,
+    Stopped,
+}
+
+fn parse_args(args: &[String]) -> Result<CliCommand, String> {
+    let mut args = args.iter().map(|a| a.as_str());
+
+    let command = match args.next() {
+        Some("list") => CliCommand::List,
+        Some("status") => CliCommand::Status,
+        Some("run") => CliCommand::Run(args.next().ok_or("Missing command name")?.into()),
+        Some("stop") => CliCommand::Stop(args.next().ok_or("Missing command name")?.into()),
+        Some("logs") => {
+            let mut name = None;
+            let mut follow = false;
+            let mut lines = DEFAULT_LOG_LINES;
+
+            while let Some(arg) = args.next() {
+                match arg {
+                    "--follow" | "-f" => follow = true,
+                    "--lines" | "-n" => {
+                        lines = args
+                            .next()
+                            .and_then(|n| n.parse().ok())
+                            .ok_or("--lines needs a number")?;
+                    }
+                    _ if name.is_none() => name = Some(arg.to_string()),
+                    _ => return Err(format!("Unexpected argument `{}`", arg)),
+                }
+            }
+
+            CliCommand::Logs {
+                name: name.ok_or("Missing command name")?,
+                follow,
+                lines,
+            }
+        }
+        Some(other) => return Err(format!("Unknown command `{}`", other)),
+        None => return Err("Missing command".into()),
+    };
+
+    match args.next() {
+        Some(arg) => Err(format!("Unexpected argument `{}`", arg)),
+        None => Ok(command),
+    }
+}
+
+// Same place the app uses in release builds
+fn data_dir() -> Result<PathBuf, String> {
+    if let Some(dir) = env::var_os("LAUNCHPANE_DATA_DIR") {
+        return Ok(dir.into());
+    }
+
+    ProjectDirs::from("com", "Adimaja", "launchpane")
+        .map(|dirs| dirs.data_local_dir().to_path_buf())
+        .ok_or_else(|| "Can't find the data directory".into())
+}
+
+async fn connect_db(data_dir: &Path) -> Result<PrismaClient, String> {
+    let db_path = data_dir.join("app.db");
+
+    // The app creates and migrates the database, we only use it
+    if !db_path.exists() {
+        return Err(format!(
+            "No database at {}, start Launchpane at least once first",
+            db_path.to_string_lossy()
+        ));
+    }
+
+    PrismaClient::_builder()
+        .with_url(format!(
+            "file:{}?connection_limit=1",
+            db_path.to_string_lossy()
+        ))
+        .build()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+fn display_name(command: &command::Data) -> &str {
+    if command.name.is_empty() {
+        &command.command
+    } else {
+        &command.name
+    }
+}
+
+async fn find_command(client: &PrismaClient, name: &str) -> Result<command::Data, String> {
+    let mut commands = client
+        .command()
+        .find_many(vec![])
+        .order_by(command::order::order(Direction::Asc))
+        .exec()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // A command can be named like the id of another one, the name wins then
+    let by_name = commands.iter().position(|c| display_name(c) == name);
+    let by_id = || {
+        let id = name.parse::<i32>().ok()?;
+        commands.iter().position(|c| c.id == id)
+    };
+
+    match by_name.or_else(by_id) {
+        Some(index) => Ok(commands.swap_remove(index)),
+        None => Err(format!("No command named `{}`", name)),
+    }
+}
+
+fn print_log_line(log_line: &command_log_line::Data) {
+    if log_line.source == direct::STDERR {
+        eprintln!("{}", log_line.line);
+    } else {
+        println!("{}", log_line.line);
+    }
+}
+
+async fn print_logs(
+    client: &PrismaClient,
+    command_id: i32,
+    follow: bool,
+    lines: i64,
+) -> Result<(), String> {
+    // In the order the lines arrived, like the app shows them
+    let query = |direction: Direction, after_id: i32| {
+        client
+            .command_log_line()
+            .find_many(vec![
+                command_log_line::command_id::equals(command_id),
+                command_log_line::id::gt(after_id),
+            ])
+            .order_by(command_log_line::run::order(direction))
+            .order_by(command_log_line::sequence::order(direction))
+            .order_by(command_log_line::id::order(direction))
+    };
+
+    let mut log_lines = query(Direction::Desc, 0)
+        .take(lines)
+        .exec()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    log_lines.reverse();
+
+    let mut last_id = 0;
+
+    for log_line in log_lines.iter() {
+        print_log_line(log_line);
+        last_id = log_line.id;
+    }
+
+    while follow {
+        // Ids are never reused, so this keeps working when the lines it has seen are cleared
+        let log_lines = query(Direction::Asc, last_id)
+            .take(FOLLOW_PAGE_SIZE)
+            .exec()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        for log_line in log_lines.iter() {
+            print_log_line(log_line);
+            last_id = last_id.max(log_line.id);
+        }
+
+        // Only wait when caught up, a full page means there are more lines already
+        if (log_lines.len() as i64) < FOLLOW_PAGE_SIZE {
+            sleep(FOLLOW_INTERVAL).await;
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_status(status: &ProcessStatus) -> String {
+    match status {
+        ProcessStatus::Running { instances: 1 } => "running".into(),
+        ProcessStatus::Running { instances } => format!("running ({} instances)", instances),
+        ProcessStatus::Stopping => "stopping".into(),
+        ProcessStatus::Stopped => "stopped".into(),
+    }
+}
+
+fn describe_last_run(command: &command::Data) -> String {
+    match (
+        command.last_run_result_type.as_deref(),
+        command.last_run_code.as_deref(),
+    ) {
+        (Some("exit"), Some(code)) => format!(", last exited with {}", code),
+        (Some("exit"), None) => ", last exited".into(),
+        (Some("killed"), _) => ", last killed".into(),
+        (Some("error"), _) => ", last failed to start".into(),
+        _ => "".into(),
+    }
+}
+
+async fn run(cli_command: CliCommand) -> Result<i32, String> {
+    let data_dir = data_dir()?;
+    let client = connect_db(&data_dir).await?;
+    let mut control = ControlClient::connect(&data_dir).await;
+
+    match cli_command {
+        CliCommand::List => {
+            let commands = client
+                .command()
+                .find_many(vec![])
+                .order_by(command::order::order(Direction::Asc))
+                .exec()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for command in commands.iter() {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    command.id, command.name, command.cwd, command.command
+                );
+            }
+        }
+        CliCommand::Run(name) => {
+            let command = find_command(&client, &name).await?;
+
+            match control.as_mut() {
+                Some(control) => {
+                    control
+                        .call::<()>("run_process", json!({ "command_id": command.id }))
+                        .await?;
+
+                    println!("Started {}", display_name(&command));
+                }
+                None => return direct::run_in_foreground(&client, &command).await,
+            }
+        }
+        CliCommand::Stop(name) => {
+            let command = find_command(&client, &name).await?;
+
+            match control.as_mut() {
+                Some(control) => {
+                    control
+                        .call::<()>("kill_process", json!({ "command_id": command.id }))
+                        .await?;
+
+                    println!("Stopped {}", display_name(&command));
+                }
+                None => return Err("Launchpane isn't running, so nothing is running".into()),
+            }
+        }
+        CliCommand::Status => {
+            let commands = client
+                .command()
+                .find_many(vec![])
+                .order_by(command::order::order(Direction::Asc))
+                .exec()
+                .await
+                .map_err(|e| e.to_string())?;
+
+            for command in commands.iter() {
+                // Without the app there's no way to tell what's running
+                let status = match control.as_mut() {
+                    Some(control) => describe_status(
+                        &control
+                            .call("get_process_status", json!({ "command_id": command.id }))
+                            .await?,
+                    ),
+                    None => "unknown, Launchpane isn't running".into(),
+                };
+
+                println!(
+                    "{}\t{}{}",
+                    display_name(command),
+                    status,
+                    describe_last_run(command)
+                );
+            }
+        }
+        CliCommand::Logs {
+            name,
+            follow,
+            lines,
+        } => {
+            let command = find_command(&client, &name).await?;
+
+            print_logs(&client, command.id, follow, lines).await?;
+        }
+    }
+
+    Ok(0)
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let cli_command = match parse_args(&args) {
+        Ok(cli_command) => cli_command,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            exit(2);
+        }
+    };
+
+    match run(cli_command).await {
+        Ok(code) => exit(code),
+        Err(err) => {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliCommand, String> {
+        parse_args(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse(&["list"]), Ok(CliCommand::List));
+        assert_eq!(parse(&["run", "api"]), Ok(CliCommand::Run("api".into())));
+        assert_eq!(
+            parse(&["logs", "-f", "api", "--lines", "20"]),
+            Ok(CliCommand::Logs {
+                name: "api".into(),
+                follow: true,
+                lines: 20,
+            })
+        );
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["run"]).is_err());
+        assert!(parse(&["stop", "api", "worker"]).is_err());
+        assert!(parse(&["logs", "api", "--lines", "many"]).is_err());
+    }
+}
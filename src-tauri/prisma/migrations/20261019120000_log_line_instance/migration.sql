@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "CommandLogLine" ADD COLUMN "instance" INTEGER;
@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "shell" TEXT;
+ALTER TABLE "Command" ADD COLUMN "shellPath" TEXT;
+ALTER TABLE "Command" ADD COLUMN "shellLogin" BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE "Command" ADD COLUMN "shellInteractive" BOOLEAN NOT NULL DEFAULT false;
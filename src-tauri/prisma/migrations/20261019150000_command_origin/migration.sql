@@ -0,0 +1,7 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "origin" TEXT;
+ALTER TABLE "Command" ADD COLUMN "originName" TEXT;
+ALTER TABLE "Command" ADD COLUMN "orphaned" BOOLEAN NOT NULL DEFAULT false;
+
+-- CreateIndex
+CREATE INDEX "Command_origin_idx" ON "Command"("origin");
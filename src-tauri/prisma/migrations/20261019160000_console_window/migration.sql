@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "consoleWindowX" REAL;
+ALTER TABLE "Command" ADD COLUMN "consoleWindowY" REAL;
+ALTER TABLE "Command" ADD COLUMN "consoleWindowWidth" REAL;
+ALTER TABLE "Command" ADD COLUMN "consoleWindowHeight" REAL;
@@ -0,0 +1,11 @@
+-- CreateTable
+CREATE TABLE "CommandNotificationRule" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "commandId" INTEGER NOT NULL,
+    "kind" TEXT NOT NULL,
+    "pattern" TEXT,
+    CONSTRAINT "CommandNotificationRule_commandId_fkey" FOREIGN KEY ("commandId") REFERENCES "Command" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "CommandNotificationRule_commandId_idx" ON "CommandNotificationRule"("commandId");
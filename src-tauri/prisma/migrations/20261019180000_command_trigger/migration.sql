@@ -0,0 +1,14 @@
+-- CreateTable
+CREATE TABLE "CommandTrigger" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "commandId" INTEGER NOT NULL,
+    "pattern" TEXT NOT NULL,
+    "source" INTEGER,
+    "action" TEXT NOT NULL,
+    "targetCommandId" INTEGER,
+    CONSTRAINT "CommandTrigger_commandId_fkey" FOREIGN KEY ("commandId") REFERENCES "Command" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "CommandTrigger_targetCommandId_fkey" FOREIGN KEY ("targetCommandId") REFERENCES "Command" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE INDEX "CommandTrigger_commandId_idx" ON "CommandTrigger"("commandId");
@@ -0,0 +1,8 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "logParser" TEXT;
+
+-- AlterTable
+ALTER TABLE "CommandLogLine" ADD COLUMN "level" TEXT;
+ALTER TABLE "CommandLogLine" ADD COLUMN "message" TEXT;
+ALTER TABLE "CommandLogLine" ADD COLUMN "loggedAt" TEXT;
+ALTER TABLE "CommandLogLine" ADD COLUMN "fields" TEXT;
@@ -0,0 +1,7 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "levelPatterns" TEXT;
+ALTER TABLE "Command" ADD COLUMN "lastRunErrorCount" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "Command" ADD COLUMN "lastRunWarningCount" INTEGER NOT NULL DEFAULT 0;
+
+-- CreateIndex
+CREATE INDEX "CommandLogLine_commandId_level_idx" ON "CommandLogLine"("commandId", "level");
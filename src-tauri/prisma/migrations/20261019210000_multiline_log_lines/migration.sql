@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "multilineMode" TEXT;
+ALTER TABLE "Command" ADD COLUMN "multilinePattern" TEXT;
+
+-- AlterTable
+ALTER TABLE "CommandLogLine" ADD COLUMN "lineCount" INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,6 @@
+-- AlterTable
+ALTER TABLE "CommandLogLine" ADD COLUMN "run" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "CommandLogLine" ADD COLUMN "sequence" INTEGER NOT NULL DEFAULT 0;
+
+-- CreateIndex
+CREATE INDEX "CommandLogLine_commandId_run_sequence_idx" ON "CommandLogLine"("commandId", "run", "sequence");
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "Command" ADD COLUMN "mergeStderr" BOOLEAN NOT NULL DEFAULT false;
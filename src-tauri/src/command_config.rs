@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use prisma_client_rust::Direction;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::fs;
+
+use crate::{
+    errors::AppCommandError,
+    prisma::{_prisma::PrismaClient, command},
+    shell::command_env,
+    utils::get_midpoint_string,
+};
+
+#[derive(Debug, Deserialize, Type, Clone, Copy)]
+pub enum CommandConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+// What to do with an imported command that has the same name as an existing one
+#[derive(Debug, Deserialize, Type, Clone, Copy)]
+pub enum ImportMergeStrategy {
+    Skip,
+    Overwrite,
+    Duplicate,
+}
+
+fn default_cwd() -> String {
+    ".".into()
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CommandDefinition {
+    pub name: String,
+    pub command: String,
+    #[serde(default = "default_cwd")]
+    pub cwd: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+// Commands are listed in their display order, the order keys themselves aren't portable
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CommandConfigFile {
+    pub commands: Vec<CommandDefinition>,
+}
+
+fn config_error(err: impl ToString) -> AppCommandError {
+    AppCommandError::ConfigError(err.to_string())
+}
+
+pub fn serialize_config(
+    config: &CommandConfigFile,
+    format: CommandConfigFormat,
+) -> Result<String, AppCommandError> {
+    match format {
+        CommandConfigFormat::Yaml => serde_yaml::to_string(config).map_err(config_error),
+        CommandConfigFormat::Toml => toml::to_string_pretty(config).map_err(config_error),
+        CommandConfigFormat::Json => serde_json::to_string_pretty(config).map_err(config_error),
+    }
+}
+
+pub fn deserialize_config(
+    content: &str,
+    format: CommandConfigFormat,
+) -> Result<CommandConfigFile, AppCommandError> {
+    match format {
+        CommandConfigFormat::Yaml => serde_yaml::from_str(content).map_err(config_error),
+        CommandConfigFormat::Toml => toml::from_str(content).map_err(config_error),
+        CommandConfigFormat::Json => serde_json::from_str(content).map_err(config_error),
+    }
+}
+
+// Paths inside the project root are stored relative to it, so the file works on every checkout
+fn relative_cwd(cwd: &str, project_root: Option<&Path>) -> String {
+    project_root
+        .and_then(|root| Path::new(cwd).strip_prefix(root).ok())
+        .map(|relative| match relative.to_string_lossy() {
+            r if r.is_empty() => ".".into(),
+            r => r.into_owned(),
+        })
+        .unwrap_or_else(|| cwd.into())
+}
+
+// Relative paths can only be resolved against a project root, there's nothing else they could
+// sensibly be relative to
+pub fn absolute_cwd(cwd: &str, project_root: Option<&Path>) -> Result<String, AppCommandError> {
+    if Path::new(cwd).is_absolute() {
+        return Ok(cwd.into());
+    }
+
+    match project_root {
+        Some(root) => {
+            let path: PathBuf = if cwd == "." {
+                root.into()
+            } else {
+                root.join(cwd)
+            };
+            Ok(path.to_string_lossy().into())
+        }
+        None => Err(config_error(format!(
+            "The working directory `{}` is relative, choose a project root to import it",
+            cwd
+        ))),
+    }
+}
+
+pub fn env_to_json(env: &BTreeMap<String, String>) -> Result<Option<String>, AppCommandError> {
+    if env.is_empty() {
+        Ok(None)
+    } else {
+        serde_json::to_string(env).map(Some).map_err(config_error)
+    }
+}
+
+pub async fn export_commands(
+    client: &PrismaClient,
+    path: &str,
+    format: CommandConfigFormat,
+    project_root: Option<&str>,
+) -> Result<u32, AppCommandError> {
+    let project_root = project_root.map(Path::new);
+
+    let commands = client
+        .command()
+        .find_many(vec![])
+        .order_by(command::order::order(Direction::Asc))
+        .exec()
+        .await?;
+
+    let definitions = commands
+        .iter()
+        .map(|c| {
+            Ok(CommandDefinition {
+                name: c.name.clone(),
+                command: c.command.clone(),
+                cwd: relative_cwd(&c.cwd, project_root),
+                env: command_env(c).map_err(config_error)?.into_iter().collect(),
+            })
+        })
+        .collect::<Result<Vec<_>, AppCommandError>>()?;
+
+    let count = definitions.len() as u32;
+
+    fs::write(
+        path,
+        serialize_config(
+            &CommandConfigFile {
+                commands: definitions,
+            },
+            format,
+        )?,
+    )
+    .await?;
+
+    Ok(count)
+}
+
+// Returns the commands that were created or changed
+pub async fn import_commands(
+    client: &PrismaClient,
+    path: &str,
+    format: CommandConfigFormat,
+    project_root: Option<&str>,
+    merge_strategy: ImportMergeStrategy,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    let config = deserialize_config(&fs::read_to_string(path).await?, format)?;
+    let project_root = project_root.map(PathBuf::from);
+
+    client
+        ._transaction()
+        .run(|client| async move {
+            let existing_commands = client.command().find_many(vec![]).exec().await?;
+
+            let mut last_order = existing_commands
+                .iter()
+                .map(|c| c.order.clone())
+                .max()
+                .unwrap_or_default();
+
+            let mut results = vec![];
+
+            for definition in config.commands {
+                let cwd = absolute_cwd(&definition.cwd, project_root.as_deref())?;
+                let env = env_to_json(&definition.env)?;
+
+                let existing = existing_commands
+                    .iter()
+                    .find(|c| !definition.name.is_empty() && c.name == definition.name);
+
+                match (existing, merge_strategy) {
+                    (Some(_), ImportMergeStrategy::Skip) => {}
+                    (Some(existing), ImportMergeStrategy::Overwrite) => {
+                        let result = client
+                            .command()
+                            .update(
+                                command::id::equals(existing.id),
+                                vec![
+                                    command::command::set(definition.command),
+                                    command::cwd::set(cwd),
+                                    command::env::set(env),
+                                ],
+                            )
+                            .exec()
+                            .await?;
+
+                        results.push(result);
+                    }
+                    (None, _) | (Some(_), ImportMergeStrategy::Duplicate) => {
+                        let order = get_midpoint_string(last_order.as_str(), "");
+
+                        let result = client
+                            .command()
+                            .create(
+                                definition.name,
+                                cwd,
+                                definition.command,
+                                order.clone(),
+                                vec![command::env::set(env)],
+                            )
+                            .exec()
+                            .await?;
+
+                        last_order = order;
+                        results.push(result);
+                    }
+                }
+            }
+
+            Ok::<Vec<command::Data>, AppCommandError>(results)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = CommandConfigFile {
+            commands: vec![
+                CommandDefinition {
+                    name: "api".into(),
+                    command: "npm run dev".into(),
+                    cwd: "packages/api".into(),
+                    env: BTreeMap::from([("PORT".into(), "3000".into())]),
+                },
+                CommandDefinition {
+                    name: "worker".into(),
+                    command: "cargo run".into(),
+                    cwd: ".".into(),
+                    env: BTreeMap::new(),
+                },
+            ],
+        };
+
+        for format in [
+            CommandConfigFormat::Yaml,
+            CommandConfigFormat::Toml,
+            CommandConfigFormat::Json,
+        ] {
+            let content = serialize_config(&config, format).unwrap();
+            assert_eq!(deserialize_config(&content, format).unwrap(), config);
+        }
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_project_relative_cwd() {
+        let root = Some(Path::new("/home/me/project"));
+
+        assert_eq!(relative_cwd("/home/me/project/api", root), "api");
+        assert_eq!(relative_cwd("/home/me/project", root), ".");
+        assert_eq!(relative_cwd("/srv/other", root), "/srv/other");
+        assert_eq!(
+            relative_cwd("/home/me/project/api", None),
+            "/home/me/project/api"
+        );
+
+        assert_eq!(absolute_cwd("api", root).unwrap(), "/home/me/project/api");
+        assert_eq!(absolute_cwd(".", root).unwrap(), "/home/me/project");
+        assert_eq!(absolute_cwd("/srv/other", root).unwrap(), "/srv/other");
+        assert_eq!(absolute_cwd("/srv/other", None).unwrap(), "/srv/other");
+        assert!(absolute_cwd("api", None).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use log::debug;
+use tauri::{
+    async_runtime::{spawn, JoinHandle},
+    AppHandle, Manager, Runtime, Window, WindowBuilder, WindowEvent, WindowUrl,
+};
+use tokio::time::{sleep, Duration};
+
+use crate::{
+    errors::AppCommandError,
+    prisma::{_prisma::PrismaClient, command},
+};
+
+const CONSOLE_WINDOW_LABEL_PREFIX: &str = "console-";
+
+const DEFAULT_WIDTH: f64 = 900.0;
+const DEFAULT_HEIGHT: f64 = 600.0;
+
+// Moving or resizing a window sends a stream of events, only save once it settles
+const GEOMETRY_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+pub fn console_window_label(command_id: i32) -> String {
+    format!("{}{}", CONSOLE_WINDOW_LABEL_PREFIX, command_id)
+}
+
+pub fn console_window_command_id(label: &str) -> Option<i32> {
+    label
+        .strip_prefix(CONSOLE_WINDOW_LABEL_PREFIX)?
+        .parse()
+        .ok()
+}
+
+// Opens a window showing only the console of the command, or focuses it if it's already open
+pub fn open_console_window<R: Runtime>(
+    app: &AppHandle<R>,
+    command: &command::Data,
+) -> Result<(), AppCommandError> {
+    let label = console_window_label(command.id);
+
+    if let Some(window) = app.get_window(&label) {
+        window.unminimize()?;
+        window.set_focus()?;
+        return Ok(());
+    }
+
+    let title = if command.name.is_empty() {
+        &command.command
+    } else {
+        &command.name
+    };
+
+    let mut builder = WindowBuilder::new(
+        app,
+        label,
+        WindowUrl::App(format!("console/{}", command.id).into()),
+    )
+    .title(format!("{} - Launchpane", title))
+    .inner_size(
+        command.console_window_width.unwrap_or(DEFAULT_WIDTH),
+        command.console_window_height.unwrap_or(DEFAULT_HEIGHT),
+    )
+    .file_drop_enabled(false);
+
+    if let (Some(x), Some(y)) = (command.console_window_x, command.console_window_y) {
+        builder = builder.position(x, y);
+    }
+
+    builder.build()?;
+
+    Ok(())
+}
+
+async fn save_console_window_geometry<R: Runtime>(
+    client: &PrismaClient,
+    window: &Window<R>,
+    command_id: i32,
+) -> Result<(), AppCommandError> {
+    let scale_factor = window.scale_factor()?;
+    let position = window.outer_position()?.to_logical::<f64>(scale_factor);
+    let size = window.inner_size()?.to_logical::<f64>(scale_factor);
+
+    client
+        .command()
+        .update(
+            command::id::equals(command_id),
+            vec![
+                command::console_window_x::set(Some(position.x)),
+                command::console_window_y::set(Some(position.y)),
+                command::console_window_width::set(Some(size.width)),
+                command::console_window_height::set(Some(size.height)),
+            ],
+        )
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+// Remembers where each console window was moved or resized to
+#[derive(Default)]
+pub struct ConsoleWindowGeometry {
+    pending_saves: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl ConsoleWindowGeometry {
+    pub fn handle_window_event<R: Runtime>(
+        &self,
+        window: &Window<R>,
+        event: &WindowEvent,
+        client: Arc<PrismaClient>,
+    ) -> Result<(), AppCommandError> {
+        let Some(command_id) = console_window_command_id(window.label()) else {
+            return Ok(());
+        };
+
+        match event {
+            WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+                let save_window = window.clone();
+                let handle = spawn(async move {
+                    sleep(GEOMETRY_SAVE_DEBOUNCE).await;
+
+                    // The window or the command might be gone by now, which is fine
+                    if let Err(err) =
+                        save_console_window_geometry(&client, &save_window, command_id).await
+                    {
+                        debug!(
+                            "Failed to save console window geometry: {}",
+                            serde_json::to_string(&err).unwrap_or_default()
+                        );
+                    }
+                });
+
+                if let Some(previous) = self
+                    .pending_saves
+                    .lock()?
+                    .insert(window.label().to_owned(), handle)
+                {
+                    previous.abort();
+                }
+            }
+            WindowEvent::Destroyed => {
+                self.pending_saves.lock()?.remove(window.label());
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,559 @@
+// A JSON-RPC 2.0 server on a Unix socket in the data dir, one request per line, so other tools
+// (like the `launchpane` CLI) can control the app. Requests are handled by the same functions as
+// the Tauri commands, so the app's state and events stay consistent.
+
+use std::{
+    fs::{self, Permissions},
+    io,
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use log::{debug, error, info};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    spawn,
+};
+
+use crate::{
+    command_config::{CommandConfigFormat, ImportMergeStrategy},
+    errors::AppCommandError,
+    log_export::{LogExportFormat, LogExportRange},
+    log_level::LogLevel,
+    merged_logs::MergedLogCursor,
+    notifications::NotificationRuleKind,
+    script_import::ScriptImportSource,
+    triggers::TriggerActionKind,
+    CommandUpdateData,
+};
+
+pub const CONTROL_SOCKET_FILE_NAME: &str = "launchpane.sock";
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+// Errors returned by the command itself, `data` contains the serialized AppCommandError
+const COMMAND_ERROR: i32 = 1;
+
+#[derive(Deserialize)]
+struct ControlRequest {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct ControlError {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+#[derive(Serialize)]
+struct ControlResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ControlError>,
+}
+
+#[derive(Deserialize)]
+struct CommandIdParams {
+    command_id: i32,
+}
+
+#[derive(Deserialize)]
+struct LogLinesParams {
+    command_id: i32,
+    #[serde(default)]
+    min_level: Option<LogLevel>,
+}
+
+#[derive(Deserialize)]
+struct NewerLogLinesParams {
+    command_id: i32,
+    last_id: i32,
+    #[serde(default)]
+    min_level: Option<LogLevel>,
+}
+
+#[derive(Deserialize)]
+struct OlderLogLinesParams {
+    command_id: i32,
+    first_id: i32,
+    #[serde(default)]
+    min_level: Option<LogLevel>,
+}
+
+#[derive(Deserialize)]
+struct MergedLogLinesParams {
+    command_ids: Vec<i32>,
+    #[serde(default)]
+    cursor: Option<MergedLogCursor>,
+}
+
+#[derive(Deserialize)]
+struct ExportLogsParams {
+    command_id: i32,
+    format: LogExportFormat,
+    range: LogExportRange,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct UpdateCommandParams {
+    command_id: i32,
+    data: CommandUpdateData,
+}
+
+#[derive(Deserialize)]
+struct MoveCommandParams {
+    command_id: i32,
+    prev_command_id: Option<i32>,
+    next_command_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct ScaleProcessParams {
+    command_id: i32,
+    instance_count: u32,
+}
+
+#[derive(Deserialize)]
+struct ExportCommandsParams {
+    path: String,
+    format: CommandConfigFormat,
+    #[serde(default)]
+    project_root: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ImportCommandsParams {
+    path: String,
+    format: CommandConfigFormat,
+    #[serde(default)]
+    project_root: Option<String>,
+    merge_strategy: ImportMergeStrategy,
+}
+
+#[derive(Deserialize)]
+struct DirectoryParams {
+    directory: String,
+}
+
+#[derive(Deserialize)]
+struct ImportScriptsParams {
+    directory: String,
+    source: ScriptImportSource,
+}
+
+#[derive(Deserialize)]
+struct CreateNotificationRuleParams {
+    command_id: i32,
+    kind: NotificationRuleKind,
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateNotificationRuleParams {
+    rule_id: i32,
+    kind: NotificationRuleKind,
+    #[serde(default)]
+    pattern: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RuleIdParams {
+    rule_id: i32,
+}
+
+#[derive(Deserialize)]
+struct CreateTriggerParams {
+    command_id: i32,
+    pattern: String,
+    #[serde(default)]
+    source: Option<i32>,
+    action: TriggerActionKind,
+    #[serde(default)]
+    target_command_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct UpdateTriggerParams {
+    trigger_id: i32,
+    pattern: String,
+    #[serde(default)]
+    source: Option<i32>,
+    action: TriggerActionKind,
+    #[serde(default)]
+    target_command_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct TriggerIdParams {
+    trigger_id: i32,
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, ControlError> {
+    serde_json::from_value(params).map_err(|e| ControlError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+        data: None,
+    })
+}
+
+fn to_result<T: Serialize, E: Serialize>(result: Result<T, E>) -> Result<Value, ControlError> {
+    match result {
+        Ok(value) => Ok(serde_json::to_value(value).unwrap_or_default()),
+        Err(err) => Err(ControlError {
+            code: COMMAND_ERROR,
+            message: "Command failed".into(),
+            data: serde_json::to_value(err).ok(),
+        }),
+    }
+}
+
+// Method names and parameter names are the same as the Tauri commands. Everything except the
+// window management commands is available.
+async fn dispatch(app: &AppHandle, method: &str, params: Value) -> Result<Value, ControlError> {
+    match method {
+        "get_commands" => to_result(crate::get_commands(app.state()).await),
+        "get_command" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::get_command(app.state(), params.command_id).await)
+        }
+        "create_command" => to_result(crate::create_command(app.state(), app.clone()).await),
+        "update_command" => {
+            let params: UpdateCommandParams = parse_params(params)?;
+            to_result(
+                crate::update_command(app.state(), app.clone(), params.command_id, params.data)
+                    .await,
+            )
+        }
+        "move_command_between" => {
+            let params: MoveCommandParams = parse_params(params)?;
+            to_result(
+                crate::move_command_between(
+                    app.state(),
+                    app.clone(),
+                    params.command_id,
+                    params.prev_command_id,
+                    params.next_command_id,
+                )
+                .await,
+            )
+        }
+        "delete_command" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::delete_command(app.state(), app.clone(), params.command_id).await)
+        }
+        "get_command_log_lines" => {
+            let params: LogLinesParams = parse_params(params)?;
+            to_result(
+                crate::get_command_log_lines(app.state(), params.command_id, params.min_level)
+                    .await,
+            )
+        }
+        "get_newer_command_log_lines" => {
+            let params: NewerLogLinesParams = parse_params(params)?;
+            to_result(
+                crate::get_newer_command_log_lines(
+                    app.state(),
+                    params.command_id,
+                    params.last_id,
+                    params.min_level,
+                )
+                .await,
+            )
+        }
+        "get_older_command_log_lines" => {
+            let params: OlderLogLinesParams = parse_params(params)?;
+            to_result(
+                crate::get_older_command_log_lines(
+                    app.state(),
+                    params.command_id,
+                    params.first_id,
+                    params.min_level,
+                )
+                .await,
+            )
+        }
+        "get_merged_log_lines" => {
+            let params: MergedLogLinesParams = parse_params(params)?;
+            to_result(
+                crate::get_merged_log_lines(app.state(), params.command_ids, params.cursor).await,
+            )
+        }
+        "export_command_logs" => {
+            let params: ExportLogsParams = parse_params(params)?;
+            to_result(
+                crate::export_command_logs(
+                    app.state(),
+                    params.command_id,
+                    params.format,
+                    params.range,
+                    params.path,
+                )
+                .await,
+            )
+        }
+        "get_process_status" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::get_process_status(app.state(), params.command_id).await)
+        }
+        "run_process" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::run_process(app.state(), params.command_id).await)
+        }
+        "restart_process" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::restart_process(app.state(), params.command_id).await)
+        }
+        "scale_process" => {
+            let params: ScaleProcessParams = parse_params(params)?;
+            to_result(
+                crate::scale_process(app.state(), params.command_id, params.instance_count).await,
+            )
+        }
+        "kill_process" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::kill_process(app.state(), params.command_id).await)
+        }
+        "refresh_shell_environment" => {
+            to_result(crate::refresh_shell_environment(app.state()).await)
+        }
+        "get_database_stats" => to_result(crate::get_database_stats(app.state()).await),
+        "clear_command_logs" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::clear_command_logs(app.state(), app.clone(), params.command_id).await)
+        }
+        "clear_all_logs" => to_result(crate::clear_all_logs(app.state(), app.clone()).await),
+        "optimize_database" => to_result(crate::optimize_database(app.state()).await),
+        "export_commands" => {
+            let params: ExportCommandsParams = parse_params(params)?;
+            to_result(
+                crate::export_commands(
+                    app.state(),
+                    params.path,
+                    params.format,
+                    params.project_root,
+                )
+                .await,
+            )
+        }
+        "import_commands" => {
+            let params: ImportCommandsParams = parse_params(params)?;
+            to_result(
+                crate::import_commands(
+                    app.state(),
+                    app.clone(),
+                    params.path,
+                    params.format,
+                    params.project_root,
+                    params.merge_strategy,
+                )
+                .await,
+            )
+        }
+        "scan_project_directory" => {
+            let params: DirectoryParams = parse_params(params)?;
+            to_result(
+                crate::scan_project_directory(app.state(), app.clone(), params.directory).await,
+            )
+        }
+        "import_scripts" => {
+            let params: ImportScriptsParams = parse_params(params)?;
+            to_result(
+                crate::import_scripts(app.state(), app.clone(), params.directory, params.source)
+                    .await,
+            )
+        }
+        "get_notification_rules" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::get_notification_rules(app.state(), params.command_id).await)
+        }
+        "create_notification_rule" => {
+            let params: CreateNotificationRuleParams = parse_params(params)?;
+            to_result(
+                crate::create_notification_rule(
+                    app.state(),
+                    params.command_id,
+                    params.kind,
+                    params.pattern,
+                )
+                .await,
+            )
+        }
+        "update_notification_rule" => {
+            let params: UpdateNotificationRuleParams = parse_params(params)?;
+            to_result(
+                crate::update_notification_rule(
+                    app.state(),
+                    params.rule_id,
+                    params.kind,
+                    params.pattern,
+                )
+                .await,
+            )
+        }
+        "delete_notification_rule" => {
+            let params: RuleIdParams = parse_params(params)?;
+            to_result(crate::delete_notification_rule(app.state(), params.rule_id).await)
+        }
+        "get_triggers" => {
+            let params: CommandIdParams = parse_params(params)?;
+            to_result(crate::get_triggers(app.state(), params.command_id).await)
+        }
+        "create_trigger" => {
+            let params: CreateTriggerParams = parse_params(params)?;
+            to_result(
+                crate::create_trigger(
+                    app.state(),
+                    params.command_id,
+                    params.pattern,
+                    params.source,
+                    params.action,
+                    params.target_command_id,
+                )
+                .await,
+            )
+        }
+        "update_trigger" => {
+            let params: UpdateTriggerParams = parse_params(params)?;
+            to_result(
+                crate::update_trigger(
+                    app.state(),
+                    params.trigger_id,
+                    params.pattern,
+                    params.source,
+                    params.action,
+                    params.target_command_id,
+                )
+                .await,
+            )
+        }
+        "delete_trigger" => {
+            let params: TriggerIdParams = parse_params(params)?;
+            to_result(crate::delete_trigger(app.state(), params.trigger_id).await)
+        }
+        _ => Err(ControlError {
+            code: METHOD_NOT_FOUND,
+            message: format!("Unknown method `{}`", method),
+            data: None,
+        }),
+    }
+}
+
+async fn handle_request(app: &AppHandle, line: &str) -> ControlResponse {
+    let (id, result) = match serde_json::from_str::<ControlRequest>(line) {
+        Ok(request) => {
+            debug!("Control request {}", request.method);
+            (
+                request.id,
+                dispatch(app, &request.method, request.params).await,
+            )
+        }
+        Err(err) => (
+            Value::Null,
+            Err(ControlError {
+                code: PARSE_ERROR,
+                message: err.to_string(),
+                data: None,
+            }),
+        ),
+    };
+
+    match result {
+        Ok(result) => ControlResponse {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        },
+        Err(error) => ControlResponse {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(error),
+        },
+    }
+}
+
+async fn handle_connection(app: AppHandle, stream: UnixStream) -> Result<(), AppCommandError> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = handle_request(&app, &line).await;
+
+        let mut response = serde_json::to_vec(&response).unwrap_or_default();
+        response.push(b'\n');
+
+        writer.write_all(&response).await?;
+    }
+
+    Ok(())
+}
+
+// Removes the socket left behind by an instance that didn't shut down cleanly, but doesn't touch
+// the socket of an instance that's still running
+pub async fn remove_stale_socket(path: &Path) -> Result<(), AppCommandError> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    if UnixStream::connect(path).await.is_ok() {
+        return Err(AppCommandError::IoError(
+            "Another instance is already listening on the control socket".into(),
+        ));
+    }
+
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+pub async fn serve(app: AppHandle) -> Result<(), AppCommandError> {
+    let path = Path::new(CONTROL_SOCKET_FILE_NAME);
+
+    remove_stale_socket(path).await?;
+
+    let listener = UnixListener::bind(path)?;
+
+    // Anyone who can connect can run commands, so only our own user may
+    fs::set_permissions(path, Permissions::from_mode(0o600))?;
+
+    info!(
+        "Listening for control requests on {}",
+        path.to_string_lossy()
+    );
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let app = app.clone();
+
+        spawn(async move {
+            if let Err(err) = handle_connection(app, stream).await {
+                error!(
+                    "Error in control connection: {}",
+                    serde_json::to_string(&err).unwrap_or_default()
+                );
+            }
+        });
+    }
+}
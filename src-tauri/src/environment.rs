@@ -0,0 +1,172 @@
+use std::{collections::HashMap, sync::Arc};
+
+#[cfg(target_family = "unix")]
+use async_process::{Command, Stdio};
+#[cfg(target_family = "unix")]
+use log::{debug, info};
+#[cfg(target_family = "unix")]
+use tokio::time::{timeout, Duration};
+
+use log::error;
+use tokio::sync::RwLock;
+
+use crate::errors::AppCommandError;
+
+// Environment of the user's login shell, used as the base environment of every command.
+// None if it hasn't been captured, or can't be on this platform.
+pub type ShellEnvironment = Arc<RwLock<Option<HashMap<String, String>>>>;
+
+// These describe the capturing shell itself, not something commands should inherit
+const IGNORED_VARIABLES: [&str; 4] = ["PWD", "OLDPWD", "SHLVL", "_"];
+
+fn is_variable_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Parses the output of `env -0`, where every variable ends with a NUL so values can contain
+// newlines. Anything the rc files print (e.g. a motd) ends up in front of the first name, and
+// can contain '=' itself, so only its last line is kept.
+pub fn parse_env_output(output: &str) -> HashMap<String, String> {
+    let mut entries = output.split('\0');
+    let first = entries
+        .next()
+        .map(|entry| entry.rsplit('\n').next().unwrap_or(entry));
+
+    first
+        .into_iter()
+        .chain(entries)
+        .filter_map(|entry| entry.split_once('='))
+        .filter(|(name, _)| is_variable_name(name) && !IGNORED_VARIABLES.contains(name))
+        .map(|(name, value)| (name.into(), value.into()))
+        .collect()
+}
+
+#[cfg(target_family = "unix")]
+pub async fn capture_shell_environment() -> Result<Option<HashMap<String, String>>, AppCommandError>
+{
+    let shell = match std::env::var("SHELL") {
+        Ok(shell) if !shell.is_empty() => shell,
+        _ => {
+            debug!("SHELL is not set, not capturing shell environment");
+            return Ok(None);
+        }
+    };
+
+    let child = Command::new(&shell)
+        .arg("-lic")
+        .arg("env -0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    // rc files can do anything, including waiting forever
+    let output = timeout(Duration::from_secs(10), child.output())
+        .await
+        .map_err(|_| {
+            AppCommandError::ShellEnvironmentError(format!("`{} -lic 'env -0'` timed out", shell))
+        })??;
+
+    if !output.status.success() {
+        return Err(AppCommandError::ShellEnvironmentError(format!(
+            "`{} -lic 'env -0'` finished with {}",
+            shell, output.status
+        )));
+    }
+
+    let variables = parse_env_output(&String::from_utf8_lossy(&output.stdout));
+
+    info!(
+        "Captured {} variables from the environment of {}",
+        variables.len(),
+        shell
+    );
+
+    Ok(Some(variables))
+}
+
+// Windows apps already get the user's full environment
+#[cfg(target_family = "windows")]
+pub async fn capture_shell_environment() -> Result<Option<HashMap<String, String>>, AppCommandError>
+{
+    Ok(None)
+}
+
+pub async fn refresh_shell_environment(
+    environment: &ShellEnvironment,
+) -> Result<(), AppCommandError> {
+    let variables = capture_shell_environment().await?;
+
+    *environment.write().await = variables;
+
+    Ok(())
+}
+
+// Used at startup, where failing to capture the environment shouldn't stop the app. The rc files
+// can take a while, so the capture runs in the background while holding the write lock, anything
+// reading the environment waits until it's done.
+pub fn load_shell_environment() -> ShellEnvironment {
+    let environment: ShellEnvironment = Arc::new(RwLock::new(None));
+
+    let mut variables = Arc::clone(&environment)
+        .try_write_owned()
+        .expect("New lock should be free");
+
+    tauri::async_runtime::spawn(async move {
+        *variables = match capture_shell_environment().await {
+            Ok(variables) => variables,
+            Err(err) => {
+                error!(
+                    "Failed to capture shell environment: {}",
+                    serde_json::to_string(&err).unwrap_or_default()
+                );
+                None
+            }
+        };
+    });
+
+    environment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_output() {
+        let variables = parse_env_output(
+            "Welcome back!\nPATH=/home/me/.nvm/bin:/usr/bin\0EMPTY=\0MULTI=first\nsecond\0EQ=a=b\0PWD=/home/me\0",
+        );
+
+        assert_eq!(
+            variables.get("PATH").map(|v| v.as_str()),
+            Some("/home/me/.nvm/bin:/usr/bin")
+        );
+        assert_eq!(variables.get("EMPTY").map(|v| v.as_str()), Some(""));
+        assert_eq!(
+            variables.get("MULTI").map(|v| v.as_str()),
+            Some("first\nsecond")
+        );
+        assert_eq!(variables.get("EQ").map(|v| v.as_str()), Some("a=b"));
+        assert_eq!(variables.get("PWD"), None);
+        assert_eq!(variables.len(), 4);
+    }
+
+    #[test]
+    fn test_parse_env_output_with_noisy_prefix() {
+        let variables =
+            parse_env_output("nvm: using node=18\nLoaded 3 plugins\nHOME=/home/me\0LANG=C\0");
+
+        assert_eq!(variables.get("HOME").map(|v| v.as_str()), Some("/home/me"));
+        assert_eq!(variables.get("LANG").map(|v| v.as_str()), Some("C"));
+        assert_eq!(variables.len(), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io, time::SystemTimeError, sync::PoisonError};
+use std::{io, sync::PoisonError, time::SystemTimeError};
 
 #[cfg(target_family = "unix")]
 use nix::errno::Errno;
@@ -16,6 +16,10 @@ pub enum AppCommandError {
     SystemTimeError(String),
     JoinError(String),
     PoisonError(String),
+    ShellEnvironmentError(String),
+    ConfigError(String),
+    WatchError(String),
+    PatternError(String),
 
     #[cfg(target_family = "unix")]
     NixError(i32),
@@ -25,6 +29,13 @@ pub enum AppCommandError {
 pub enum ClientError {
     CommandNotFound,
     InvalidCommandId,
+    AlreadyRunning,
+    InvalidLogRange,
+    ProjectConfigNotFound,
+    MissingPattern,
+    MissingTriggerTarget,
+    // Merged stderr is stored as stdout, so triggers on stderr would never match
+    StderrMerged,
 }
 
 impl From<QueryError> for AppCommandError {
@@ -58,6 +69,18 @@ impl From<Errno> for AppCommandError {
     }
 }
 
+impl From<notify::Error> for AppCommandError {
+    fn from(err: notify::Error) -> Self {
+        Self::WatchError(err.to_string())
+    }
+}
+
+impl From<regex::Error> for AppCommandError {
+    fn from(err: regex::Error) -> Self {
+        Self::PatternError(err.to_string())
+    }
+}
+
 impl From<JoinError> for AppCommandError {
     fn from(err: JoinError) -> Self {
         Self::JoinError(err.to_string())
@@ -68,4 +91,4 @@ impl<T> From<PoisonError<T>> for AppCommandError {
     fn from(err: PoisonError<T>) -> Self {
         Self::PoisonError(err.to_string())
     }
-}
\ No newline at end of file
+}
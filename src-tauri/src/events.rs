@@ -1,19 +1,222 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use log::error;
 use serde::Serialize;
 use specta::Type;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    time::{timeout_at, Duration, Instant},
+};
+
+use crate::{
+    errors::AppCommandError, prisma::command_log_line, process::ProcessStatus,
+    tray::TrayMenuUpdater,
+};
 
-#[derive(Debug, Serialize, Type, Clone, Copy)]
+#[derive(Debug, Serialize, Type, Clone)]
 pub enum AppEventPayload {
     CommandUpdateEvent(i32),
-    CommandLogUpdateEvent(i32),
+    // New log lines of a command, in insertion order
+    CommandLogLinesEvent {
+        command_id: i32,
+        log_lines: Vec<command_log_line::Data>,
+    },
+    ProcessStatusEvent {
+        command_id: i32,
+        status: ProcessStatus,
+    },
+    // Some CommandLogLinesEvents were skipped, the lines have to be fetched again
+    LogLinesDroppedEvent,
+    // All stored lines of the command were deleted
+    CommandLogsClearedEvent(i32),
+    // The command wrote log lines, sent to every window at most once per batch
+    CommandActivityEvent(i32),
 }
 
 const EVENT_CHANNEL: &str = "change_event";
 
-pub fn send_command_update_event(app: &AppHandle, command_id: i32) -> Result<(), tauri::Error> {
-    app.emit_all(EVENT_CHANNEL, AppEventPayload::CommandUpdateEvent(command_id))
+// Log lines written within this time are sent together in one event per command
+const LOG_LINES_BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+// Which windows want log events for which commands
+#[derive(Default)]
+pub struct EventSubscriptions {
+    // Window label -> command id -> number of subscriptions from that window
+    windows: Mutex<HashMap<String, HashMap<i32, usize>>>,
+}
+
+impl EventSubscriptions {
+    pub fn subscribe(&self, window_label: &str, command_id: i32) -> Result<(), AppCommandError> {
+        *self
+            .windows
+            .lock()?
+            .entry(window_label.to_owned())
+            .or_default()
+            .entry(command_id)
+            .or_default() += 1;
+
+        Ok(())
+    }
+
+    pub fn unsubscribe(&self, window_label: &str, command_id: i32) -> Result<(), AppCommandError> {
+        let mut windows = self.windows.lock()?;
+
+        if let Some(commands) = windows.get_mut(window_label) {
+            if let Some(count) = commands.get_mut(&command_id) {
+                *count -= 1;
+                if *count == 0 {
+                    commands.remove(&command_id);
+                }
+            }
+
+            if commands.is_empty() {
+                windows.remove(window_label);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_window(&self, window_label: &str) -> Result<(), AppCommandError> {
+        self.windows.lock()?.remove(window_label);
+        Ok(())
+    }
+
+    fn subscribed_windows(&self, command_id: i32) -> Result<Vec<String>, AppCommandError> {
+        Ok(self
+            .windows
+            .lock()?
+            .iter()
+            .filter(|(_, commands)| commands.contains_key(&command_id))
+            .map(|(label, _)| label.clone())
+            .collect())
+    }
+}
+
+// Emits a log event only to the windows that subscribed to the command
+fn emit_to_subscribers<R: Runtime>(
+    app: &AppHandle<R>,
+    command_id: i32,
+    payload: AppEventPayload,
+) -> Result<(), tauri::Error> {
+    let Some(subscriptions) = app.try_state::<EventSubscriptions>() else {
+        return Ok(());
+    };
+
+    let labels = match subscriptions.subscribed_windows(command_id) {
+        Ok(labels) => labels,
+        Err(_) => {
+            error!("Event subscriptions are poisoned");
+            return Ok(());
+        }
+    };
+
+    for label in labels {
+        app.emit_to(&label, EVENT_CHANNEL, payload.clone())?;
+    }
+
+    Ok(())
 }
 
-pub fn send_command_log_update_event(app: &AppHandle, command_id: i32) -> Result<(), tauri::Error> {
-    app.emit_all(EVENT_CHANNEL, AppEventPayload::CommandLogUpdateEvent(command_id))
+pub fn send_command_update_event<R: Runtime>(
+    app: &AppHandle<R>,
+    command_id: i32,
+) -> Result<(), tauri::Error> {
+    // The tray menu shows every command, so it changes with them
+    if let Some(tray_menu_updater) = app.try_state::<TrayMenuUpdater>() {
+        tray_menu_updater.request_rebuild();
+    }
+
+    app.emit_all(
+        EVENT_CHANNEL,
+        AppEventPayload::CommandUpdateEvent(command_id),
+    )
+}
+
+pub fn send_command_logs_cleared_event<R: Runtime>(
+    app: &AppHandle<R>,
+    command_id: i32,
+) -> Result<(), tauri::Error> {
+    emit_to_subscribers(
+        app,
+        command_id,
+        AppEventPayload::CommandLogsClearedEvent(command_id),
+    )
+}
+
+pub fn send_process_status_event<R: Runtime>(
+    app: &AppHandle<R>,
+    command_id: i32,
+    status: ProcessStatus,
+) -> Result<(), tauri::Error> {
+    app.emit_all(
+        EVENT_CHANNEL,
+        AppEventPayload::ProcessStatusEvent { command_id, status },
+    )
+}
+
+// Forwards the lines written by the LogWriter to the frontend, batched per command
+pub async fn send_log_line_events<R: Runtime>(
+    app: Arc<AppHandle<R>>,
+    mut receiver: Receiver<command_log_line::Data>,
+) {
+    loop {
+        let mut batches: HashMap<i32, Vec<command_log_line::Data>> = HashMap::new();
+        let mut dropped = false;
+
+        match receiver.recv().await {
+            Ok(line) => batches.entry(line.command_id).or_default().push(line),
+            Err(RecvError::Lagged(_)) => dropped = true,
+            Err(RecvError::Closed) => return,
+        }
+
+        let deadline = Instant::now() + LOG_LINES_BATCH_INTERVAL;
+
+        while let Ok(result) = timeout_at(deadline, receiver.recv()).await {
+            match result {
+                Ok(line) => batches.entry(line.command_id).or_default().push(line),
+                Err(RecvError::Lagged(_)) => dropped = true,
+                Err(RecvError::Closed) => break,
+            }
+        }
+
+        if let Err(err) = send_batches(&app, batches, dropped) {
+            error!("Failed to send log line events: {}", err);
+        }
+    }
+}
+
+fn send_batches<R: Runtime>(
+    app: &AppHandle<R>,
+    batches: HashMap<i32, Vec<command_log_line::Data>>,
+    dropped: bool,
+) -> Result<(), tauri::Error> {
+    // Lines only go to subscribers, but every window shows which commands are active
+    for command_id in batches.keys() {
+        app.emit_all(
+            EVENT_CHANNEL,
+            AppEventPayload::CommandActivityEvent(*command_id),
+        )?;
+    }
+
+    if dropped {
+        return app.emit_all(EVENT_CHANNEL, AppEventPayload::LogLinesDroppedEvent);
+    }
+
+    for (command_id, log_lines) in batches {
+        emit_to_subscribers(
+            app,
+            command_id,
+            AppEventPayload::CommandLogLinesEvent {
+                command_id,
+                log_lines,
+            },
+        )?;
+    }
+
+    Ok(())
 }
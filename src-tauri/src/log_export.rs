@@ -0,0 +1,472 @@
+use std::io::Write;
+
+use chrono::{Local, TimeZone};
+use prisma_client_rust::{
+    operator::{and, or},
+    Direction,
+};
+use serde::Deserialize;
+use specta::Type;
+use tokio::{
+    fs::File,
+    io::{AsyncWrite, AsyncWriteExt, BufWriter},
+};
+
+use crate::{
+    errors::{AppCommandError, ClientError},
+    prisma::{_prisma::PrismaClient, command_log_line},
+    process::CommandLogLineSource,
+};
+
+// Only this many lines are kept in memory at once, logs can have millions of them
+const EXPORT_PAGE_SIZE: i64 = 5000;
+
+#[derive(Debug, Deserialize, Type, Clone, Copy)]
+pub enum LogExportFormat {
+    // Just the lines, as they were printed
+    Text,
+    // Lines prefixed with the time and an E for stderr, like the console shows them
+    TimestampedText,
+    // One JSON object per line, with all the stored fields
+    JsonLines,
+    // Like Text, but without colors and other terminal escape codes
+    AnsiStrippedText,
+}
+
+#[derive(Debug, Deserialize, Type, Clone, Copy)]
+pub enum LogExportRange {
+    All,
+    // All lines of the latest run
+    LastRun,
+    // The lines ordered from the first to the last one, both ends are inclusive
+    Between { first_id: i32, last_id: i32 },
+}
+
+// Where a line is in the log of its command
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+struct LinePosition {
+    run: i32,
+    sequence: i32,
+}
+
+// A range with the lines it refers to looked up
+#[derive(Debug, Clone, Copy)]
+enum ResolvedRange {
+    All,
+    Run(i32),
+    Between(LinePosition, LinePosition),
+}
+
+// Removes CSI sequences (colors, cursor movement), OSC sequences (titles, links)
+// and other two character escape sequences
+pub fn strip_ansi_codes(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            result.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('[') => {
+                // Parameters and intermediates, then a single final byte in @..~
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                // Terminated by BEL or ESC \
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            Some(c) if (' '..='/').contains(&c) => {
+                // Intermediate bytes, then a single final byte, e.g. ESC ( B
+                for c in chars.by_ref() {
+                    if !(' '..='/').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+fn format_timestamp(timestamp: f64) -> String {
+    match Local.timestamp_millis_opt(timestamp as i64).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+        None => timestamp.to_string(),
+    }
+}
+
+fn write_log_line(
+    writer: &mut impl Write,
+    format: LogExportFormat,
+    log_line: &command_log_line::Data,
+) -> Result<(), AppCommandError> {
+    match format {
+        LogExportFormat::Text => writeln!(writer, "{}", log_line.line)?,
+        LogExportFormat::TimestampedText => writeln!(
+            writer,
+            "{} {} {}",
+            format_timestamp(log_line.timestamp),
+            if log_line.source == CommandLogLineSource::STDERR as i32 {
+                "E"
+            } else {
+                " "
+            },
+            log_line.line
+        )?,
+        LogExportFormat::JsonLines => {
+            serde_json::to_writer(&mut *writer, log_line)
+                .map_err(|e| AppCommandError::IoError(e.to_string()))?;
+            writeln!(writer)?;
+        }
+        LogExportFormat::AnsiStrippedText => {
+            writeln!(writer, "{}", strip_ansi_codes(&log_line.line))?
+        }
+    }
+
+    Ok(())
+}
+
+async fn get_line_position(
+    client: &PrismaClient,
+    command_id: i32,
+    id: i32,
+) -> Result<LinePosition, AppCommandError> {
+    client
+        .command_log_line()
+        .find_first(vec![
+            command_log_line::id::equals(id),
+            command_log_line::command_id::equals(command_id),
+        ])
+        .exec()
+        .await?
+        .map(|log_line| LinePosition {
+            run: log_line.run,
+            sequence: log_line.sequence,
+        })
+        .ok_or(AppCommandError::ClientError(ClientError::InvalidLogRange))
+}
+
+// None if there's nothing to export, because the command has no lines yet
+async fn resolve_range(
+    client: &PrismaClient,
+    command_id: i32,
+    range: LogExportRange,
+) -> Result<Option<ResolvedRange>, AppCommandError> {
+    match range {
+        LogExportRange::All => Ok(Some(ResolvedRange::All)),
+        LogExportRange::LastRun => Ok(client
+            .command_log_line()
+            .find_first(vec![command_log_line::command_id::equals(command_id)])
+            .order_by(command_log_line::run::order(Direction::Desc))
+            .exec()
+            .await?
+            .map(|log_line| ResolvedRange::Run(log_line.run))),
+        LogExportRange::Between { first_id, last_id } => {
+            let first = get_line_position(client, command_id, first_id).await?;
+            let last = get_line_position(client, command_id, last_id).await?;
+
+            if first > last {
+                return Err(AppCommandError::ClientError(ClientError::InvalidLogRange));
+            }
+
+            Ok(Some(ResolvedRange::Between(first, last)))
+        }
+    }
+}
+
+fn range_filters(range: ResolvedRange) -> Vec<command_log_line::WhereParam> {
+    match range {
+        ResolvedRange::All => vec![],
+        ResolvedRange::Run(run) => vec![command_log_line::run::equals(run)],
+        ResolvedRange::Between(first, last) => vec![
+            or(vec![
+                command_log_line::run::gt(first.run),
+                and(vec![
+                    command_log_line::run::equals(first.run),
+                    command_log_line::sequence::gte(first.sequence),
+                ]),
+            ]),
+            or(vec![
+                command_log_line::run::lt(last.run),
+                and(vec![
+                    command_log_line::run::equals(last.run),
+                    command_log_line::sequence::lte(last.sequence),
+                ]),
+            ]),
+        ],
+    }
+}
+
+// Returns the number of lines written
+pub async fn export_command_logs(
+    client: &PrismaClient,
+    command_id: i32,
+    format: LogExportFormat,
+    range: LogExportRange,
+    path: &str,
+) -> Result<u32, AppCommandError> {
+    let range = match resolve_range(client, command_id, range).await? {
+        Some(range) => range,
+        None => return Ok(0),
+    };
+
+    // Only created once the range is known to be valid
+    let mut writer = BufWriter::new(File::create(path).await?);
+
+    let count = write_log_lines(
+        client,
+        command_id,
+        format,
+        range,
+        &mut writer,
+        EXPORT_PAGE_SIZE,
+    )
+    .await?;
+
+    writer.flush().await?;
+
+    Ok(count)
+}
+
+async fn write_log_lines(
+    client: &PrismaClient,
+    command_id: i32,
+    format: LogExportFormat,
+    range: ResolvedRange,
+    writer: &mut (impl AsyncWrite + Unpin),
+    page_size: i64,
+) -> Result<u32, AppCommandError> {
+    let mut page = vec![];
+    let mut last_id = None;
+    let mut count = 0;
+
+    loop {
+        let mut filters = vec![command_log_line::command_id::equals(command_id)];
+        filters.extend(range_filters(range));
+
+        // In the order the lines arrived, continuing after the last line of the previous page
+        let mut query = client
+            .command_log_line()
+            .find_many(filters)
+            .order_by(command_log_line::run::order(Direction::Asc))
+            .order_by(command_log_line::sequence::order(Direction::Asc))
+            .order_by(command_log_line::id::order(Direction::Asc));
+
+        if let Some(last_id) = last_id {
+            query = query.cursor(command_log_line::id::equals(last_id)).skip(1);
+        }
+
+        let log_lines = query.take(page_size).exec().await?;
+
+        // Formatted in memory, so only the file writes have to wait for the disk
+        page.clear();
+        for log_line in log_lines.iter() {
+            write_log_line(&mut page, format, log_line)?;
+        }
+        writer.write_all(&page).await?;
+
+        count += log_lines.len() as u32;
+
+        match log_lines.last() {
+            Some(log_line) if log_lines.len() as i64 == page_size => {
+                last_id = Some(log_line.id);
+            }
+            _ => break,
+        }
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use super::*;
+    use crate::{prisma::command, test_utils::TestDatabase};
+
+    async fn create_test_command(db: &TestDatabase) -> i32 {
+        db.command()
+            .create(
+                "test".into(),
+                temp_dir().to_string_lossy().into(),
+                "true".into(),
+                "n".into(),
+                vec![],
+            )
+            .exec()
+            .await
+            .unwrap()
+            .id
+    }
+
+    // Stored out of order on purpose, the export has to follow run and sequence
+    async fn insert_lines(db: &TestDatabase, command_id: i32) -> Vec<command_log_line::Data> {
+        let mut log_lines = vec![];
+
+        for (run, sequence) in [(1, 1), (1, 0), (1, 2), (2, 1), (2, 0), (1, 3), (2, 2)] {
+            let log_line = db
+                .command_log_line()
+                .create(
+                    command::id::equals(command_id),
+                    CommandLogLineSource::STDOUT as i32,
+                    format!("{}.{}", run, sequence),
+                    0.0,
+                    vec![
+                        command_log_line::run::set(run),
+                        command_log_line::sequence::set(sequence),
+                    ],
+                )
+                .exec()
+                .await
+                .unwrap();
+
+            log_lines.push(log_line);
+        }
+
+        log_lines
+    }
+
+    async fn export_to_string(
+        db: &TestDatabase,
+        command_id: i32,
+        range: LogExportRange,
+        page_size: i64,
+    ) -> Result<String, AppCommandError> {
+        let mut output = vec![];
+
+        if let Some(range) = resolve_range(db, command_id, range).await? {
+            write_log_lines(
+                db,
+                command_id,
+                LogExportFormat::Text,
+                range,
+                &mut output,
+                page_size,
+            )
+            .await?;
+        }
+
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[tokio::test]
+    async fn exports_every_page_in_order() {
+        let db = TestDatabase::new().await;
+        let command_id = create_test_command(&db).await;
+        insert_lines(&db, command_id).await;
+
+        for page_size in [1, 2, 3, 7, 100] {
+            assert_eq!(
+                export_to_string(&db, command_id, LogExportRange::All, page_size)
+                    .await
+                    .unwrap(),
+                "1.0\n1.1\n1.2\n1.3\n2.0\n2.1\n2.2\n"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_the_last_run() {
+        let db = TestDatabase::new().await;
+        let command_id = create_test_command(&db).await;
+
+        assert_eq!(
+            export_to_string(&db, command_id, LogExportRange::LastRun, 2)
+                .await
+                .unwrap(),
+            ""
+        );
+
+        insert_lines(&db, command_id).await;
+
+        assert_eq!(
+            export_to_string(&db, command_id, LogExportRange::LastRun, 2)
+                .await
+                .unwrap(),
+            "2.0\n2.1\n2.2\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn exports_lines_between_two_lines() {
+        let db = TestDatabase::new().await;
+        let command_id = create_test_command(&db).await;
+        let log_lines = insert_lines(&db, command_id).await;
+        let id_of = |line: &str| log_lines.iter().find(|l| l.line == line).unwrap().id;
+
+        // The ids of these aren't in order, their positions are
+        let range = LogExportRange::Between {
+            first_id: id_of("1.2"),
+            last_id: id_of("2.1"),
+        };
+
+        assert_eq!(
+            export_to_string(&db, command_id, range, 2).await.unwrap(),
+            "1.2\n1.3\n2.0\n2.1\n"
+        );
+
+        let reversed = LogExportRange::Between {
+            first_id: id_of("2.1"),
+            last_id: id_of("1.2"),
+        };
+
+        assert!(matches!(
+            export_to_string(&db, command_id, reversed, 2).await,
+            Err(AppCommandError::ClientError(ClientError::InvalidLogRange))
+        ));
+    }
+
+    #[tokio::test]
+    async fn invalid_ranges_create_no_file() {
+        let db = TestDatabase::new().await;
+        let command_id = create_test_command(&db).await;
+        let path = temp_dir().join(format!("launchpane-export-{}.log", command_id));
+
+        let result = export_command_logs(
+            &db,
+            command_id,
+            LogExportFormat::Text,
+            LogExportRange::Between {
+                first_id: 1,
+                last_id: 2,
+            },
+            &path.to_string_lossy(),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_strip_ansi_codes() {
+        assert_eq!(strip_ansi_codes("plain"), "plain");
+        assert_eq!(
+            strip_ansi_codes("\u{1b}[1;32mcompiled\u{1b}[0m successfully"),
+            "compiled successfully"
+        );
+        assert_eq!(
+            strip_ansi_codes("\u{1b}]8;;http://localhost\u{7}link\u{1b}]8;;\u{1b}\\ done"),
+            "link done"
+        );
+        assert_eq!(strip_ansi_codes("\u{1b}[2K\u{1b}(Bprogress"), "progress");
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use log::error;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{log_export::strip_ansi_codes, prisma::command};
+
+#[derive(
+    Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+const LEVELS: [LogLevel; 6] = [
+    LogLevel::Trace,
+    LogLevel::Debug,
+    LogLevel::Info,
+    LogLevel::Warn,
+    LogLevel::Error,
+    LogLevel::Fatal,
+];
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+            LogLevel::Fatal => "fatal",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        LEVELS.into_iter().find(|level| level.as_str() == value)
+    }
+
+    // The stored values of this level and the ones above it, for filtering
+    pub fn at_or_above(&self) -> Vec<String> {
+        LEVELS
+            .into_iter()
+            .filter(|level| level >= self)
+            .map(|level| level.as_str().into())
+            .collect()
+    }
+}
+
+// Checked in this order, the first match wins
+const DEFAULT_PATTERNS: [(LogLevel, &str); 4] = [
+    (
+        LogLevel::Error,
+        // [ERROR], ERROR:, Rust's error[E0308]:, and the first and last line of Python tracebacks
+        r"(?i)\[(error|err|fatal)\]|^\s*(error|fatal)(\[E\d+\])?:|^Traceback \(most recent call last\):|^\w+(Error|Exception): ",
+    ),
+    (
+        LogLevel::Warn,
+        r"(?i)\[(warn|warning)\]|^\s*(warn|warning)(\[\w+\])?:",
+    ),
+    (LogLevel::Info, r"(?i)\[info\]|^\s*info:"),
+    (LogLevel::Debug, r"(?i)\[(debug|trace)\]|^\s*(debug|trace):"),
+];
+
+// Classifies plain text lines using the command's patterns, or the defaults
+pub struct LevelDetector {
+    patterns: Vec<(LogLevel, Regex)>,
+}
+
+impl LevelDetector {
+    // The command can override the pattern of each level with a JSON object like
+    // {"error": "FAILED"}, an empty pattern disables detecting that level
+    pub fn from_command(command: &command::Data) -> Self {
+        let overrides: HashMap<LogLevel, String> = command
+            .level_patterns
+            .as_deref()
+            .and_then(|patterns| match serde_json::from_str(patterns) {
+                Ok(patterns) => Some(patterns),
+                Err(err) => {
+                    error!("Ignoring invalid level patterns: {}", err);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        let patterns = DEFAULT_PATTERNS
+            .into_iter()
+            .filter_map(|(level, default_pattern)| {
+                let pattern = overrides
+                    .get(&level)
+                    .map(String::as_str)
+                    .unwrap_or(default_pattern);
+
+                if pattern.is_empty() {
+                    return None;
+                }
+
+                match Regex::new(pattern) {
+                    Ok(regex) => Some((level, regex)),
+                    Err(err) => {
+                        error!("Ignoring invalid {} pattern: {}", level.as_str(), err);
+                        Regex::new(default_pattern).ok().map(|regex| (level, regex))
+                    }
+                }
+            })
+            .collect();
+
+        Self { patterns }
+    }
+
+    pub fn detect(&self, line: &str) -> Option<LogLevel> {
+        let line = strip_ansi_codes(line);
+
+        self.patterns
+            .iter()
+            .find(|(_, pattern)| pattern.is_match(&line))
+            .map(|(level, _)| *level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_detector() -> LevelDetector {
+        LevelDetector {
+            patterns: DEFAULT_PATTERNS
+                .into_iter()
+                .map(|(level, pattern)| (level, Regex::new(pattern).unwrap()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn detects_common_formats() {
+        let detector = default_detector();
+
+        let cases = [
+            ("[ERROR] connection refused", Some(LogLevel::Error)),
+            ("error[E0308]: mismatched types", Some(LogLevel::Error)),
+            ("error: could not compile `app`", Some(LogLevel::Error)),
+            ("Traceback (most recent call last):", Some(LogLevel::Error)),
+            (
+                "ValueError: invalid literal for int()",
+                Some(LogLevel::Error),
+            ),
+            ("WARN: disk almost full", Some(LogLevel::Warn)),
+            ("warning: unused variable: `x`", Some(LogLevel::Warn)),
+            ("2023-07-21 [INFO] started", Some(LogLevel::Info)),
+            ("[debug] cache miss", Some(LogLevel::Debug)),
+            ("\x1b[31m[ERROR]\x1b[0m colored", Some(LogLevel::Error)),
+            ("Listening on port 3000", None),
+            ("no errors found", None),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(detector.detect(line), expected, "{}", line);
+        }
+    }
+
+    #[test]
+    fn levels_at_or_above() {
+        assert_eq!(LogLevel::Warn.at_or_above(), vec!["warn", "error", "fatal"]);
+        assert_eq!(LogLevel::parse("error"), Some(LogLevel::Error));
+        assert_eq!(LogLevel::parse("verbose"), None);
+    }
+}
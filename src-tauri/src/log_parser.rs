@@ -0,0 +1,148 @@
+use serde_json::{Map, Value};
+
+use crate::prisma::{command, command_log_line};
+
+const LEVEL_KEYS: &[&str] = &["level", "lvl", "severity"];
+const MESSAGE_KEYS: &[&str] = &["msg", "message"];
+const TIMESTAMP_KEYS: &[&str] = &["timestamp", "time", "ts", "@timestamp"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogParserMode {
+    Plain,
+    Json,
+}
+
+impl LogParserMode {
+    pub fn from_command(command: &command::Data) -> Self {
+        match command.log_parser.as_deref() {
+            Some("json") => LogParserMode::Json,
+            _ => LogParserMode::Plain,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedLogLine {
+    pub level: Option<String>,
+    pub message: Option<String>,
+    pub logged_at: Option<String>,
+    pub fields: Option<String>,
+}
+
+impl ParsedLogLine {
+    pub fn into_params(self) -> Vec<command_log_line::SetParam> {
+        vec![
+            command_log_line::level::set(self.level),
+            command_log_line::message::set(self.message),
+            command_log_line::logged_at::set(self.logged_at),
+            command_log_line::fields::set(self.fields),
+        ]
+    }
+}
+
+// Numeric levels as used by pino and bunyan
+fn numeric_level(level: u64) -> &'static str {
+    match level {
+        0..=10 => "trace",
+        11..=20 => "debug",
+        21..=30 => "info",
+        31..=40 => "warn",
+        41..=50 => "error",
+        _ => "fatal",
+    }
+}
+
+fn normalize_level(level: &str) -> String {
+    match level.to_lowercase().as_str() {
+        "warning" => "warn".into(),
+        "err" => "error".into(),
+        "critical" | "crit" | "panic" => "fatal".into(),
+        level => level.into(),
+    }
+}
+
+fn take_first(object: &mut Map<String, Value>, keys: &[&str]) -> Option<Value> {
+    keys.iter().find_map(|key| object.remove(*key))
+}
+
+fn value_to_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        value => value.to_string(),
+    }
+}
+
+pub fn parse_json_line(line: &str) -> Option<ParsedLogLine> {
+    // Cheap check first, most lines of a mixed output aren't JSON
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+
+    let Ok(Value::Object(mut object)) = serde_json::from_str::<Value>(line) else {
+        return None;
+    };
+
+    let level = take_first(&mut object, LEVEL_KEYS).map(|level| match level {
+        Value::Number(n) => n.as_u64().map(numeric_level).unwrap_or("info").into(),
+        level => normalize_level(&value_to_string(level)),
+    });
+    let message = take_first(&mut object, MESSAGE_KEYS).map(value_to_string);
+    let logged_at = take_first(&mut object, TIMESTAMP_KEYS).map(value_to_string);
+
+    let fields = if object.is_empty() {
+        None
+    } else {
+        Some(Value::Object(object).to_string())
+    };
+
+    Some(ParsedLogLine {
+        level,
+        message,
+        logged_at,
+        fields,
+    })
+}
+
+// Extracts what can be stored in the extra columns of an output line
+pub fn parse_line(mode: LogParserMode, line: &str) -> ParsedLogLine {
+    match mode {
+        LogParserMode::Json => parse_json_line(line).unwrap_or_default(),
+        LogParserMode::Plain => ParsedLogLine::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_line() {
+        assert_eq!(
+            parse_json_line(
+                r#"{"level":"WARNING","msg":"slow query","time":"2023-07-21T16:45:48Z","ms":1200}"#
+            ),
+            Some(ParsedLogLine {
+                level: Some("warn".into()),
+                message: Some("slow query".into()),
+                logged_at: Some("2023-07-21T16:45:48Z".into()),
+                fields: Some(r#"{"ms":1200}"#.into()),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_numeric_levels() {
+        let parsed = parse_json_line(r#"{"level":50,"msg":"boom","time":1689957948000}"#).unwrap();
+
+        assert_eq!(parsed.level.as_deref(), Some("error"));
+        assert_eq!(parsed.logged_at.as_deref(), Some("1689957948000"));
+        assert_eq!(parsed.fields, None);
+    }
+
+    #[test]
+    fn ignores_non_json_lines() {
+        assert_eq!(parse_json_line("Listening on port 3000"), None);
+        assert_eq!(parse_json_line("{ not json"), None);
+        assert_eq!(parse_json_line("[1, 2, 3]"), None);
+    }
+}
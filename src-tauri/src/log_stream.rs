@@ -0,0 +1,223 @@
+// Streams the log lines of a command as newline-delimited JSON on a Unix socket in the data dir.
+// Clients send a single request line like `{"command_id": 3, "after_id": 120}`, and receive every
+// stored line after `after_id` followed by new lines as they're stored. Without `after_id`, only
+// new lines are sent.
+
+use std::{
+    fs::{self, Permissions},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use log::{debug, error, info};
+use prisma_client_rust::Direction;
+use serde::Deserialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{unix::OwnedWriteHalf, UnixListener, UnixStream},
+    select, spawn,
+    sync::broadcast::error::RecvError,
+};
+
+use crate::{
+    control::remove_stale_socket,
+    errors::AppCommandError,
+    prisma::{_prisma::PrismaClient, command_log_line},
+    AppStateData,
+};
+
+pub const LOG_STREAM_SOCKET_FILE_NAME: &str = "launchpane-logs.sock";
+
+const STORED_LINES_PAGE_SIZE: i64 = 1000;
+
+#[derive(Deserialize)]
+struct StreamRequest {
+    command_id: i32,
+    after_id: Option<i32>,
+}
+
+async fn write_log_line(
+    writer: &mut OwnedWriteHalf,
+    log_line: &command_log_line::Data,
+) -> Result<(), AppCommandError> {
+    let mut line = serde_json::to_vec(log_line).unwrap_or_default();
+    line.push(b'\n');
+
+    writer.write_all(&line).await?;
+
+    Ok(())
+}
+
+// Sends the stored lines after `after_id` page by page, in the order they arrived, returns the id
+// of the last line sent. All of them when `after_id` is 0. The highest id sent is kept too, any
+// line above it is new.
+async fn send_stored_lines(
+    client: &PrismaClient,
+    writer: &mut OwnedWriteHalf,
+    command_id: i32,
+    after_id: i32,
+    max_id: &mut i32,
+) -> Result<i32, AppCommandError> {
+    let mut last_id = after_id;
+
+    loop {
+        let mut query = client
+            .command_log_line()
+            .find_many(vec![command_log_line::command_id::equals(command_id)])
+            .order_by(command_log_line::run::order(Direction::Asc))
+            .order_by(command_log_line::sequence::order(Direction::Asc))
+            .order_by(command_log_line::id::order(Direction::Asc));
+
+        if last_id != 0 {
+            query = query.cursor(command_log_line::id::equals(last_id)).skip(1);
+        }
+
+        let log_lines = query.take(STORED_LINES_PAGE_SIZE).exec().await?;
+
+        for log_line in log_lines.iter() {
+            write_log_line(writer, log_line).await?;
+            last_id = log_line.id;
+            *max_id = (*max_id).max(log_line.id);
+        }
+
+        if (log_lines.len() as i64) < STORED_LINES_PAGE_SIZE {
+            return Ok(last_id);
+        }
+    }
+}
+
+// The last line in the order they arrived, and the highest id
+async fn get_last_ids(
+    client: &PrismaClient,
+    command_id: i32,
+) -> Result<(i32, i32), AppCommandError> {
+    let last_id = client
+        .command_log_line()
+        .find_first(vec![command_log_line::command_id::equals(command_id)])
+        .order_by(command_log_line::run::order(Direction::Desc))
+        .order_by(command_log_line::sequence::order(Direction::Desc))
+        .order_by(command_log_line::id::order(Direction::Desc))
+        .exec()
+        .await?
+        .map(|l| l.id)
+        .unwrap_or(0);
+
+    let max_id = client
+        .command_log_line()
+        .find_first(vec![command_log_line::command_id::equals(command_id)])
+        .order_by(command_log_line::id::order(Direction::Desc))
+        .exec()
+        .await?
+        .map(|l| l.id)
+        .unwrap_or(0);
+
+    Ok((last_id, max_id))
+}
+
+async fn handle_connection(app: AppHandle, stream: UnixStream) -> Result<(), AppCommandError> {
+    let state = app.state::<AppStateData>();
+
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let request = match lines.next_line().await? {
+        Some(line) => line,
+        None => return Ok(()),
+    };
+
+    let request: StreamRequest = match serde_json::from_str(&request) {
+        Ok(request) => request,
+        Err(err) => {
+            let mut response =
+                serde_json::to_vec(&json!({ "error": err.to_string() })).unwrap_or_default();
+            response.push(b'\n');
+            writer.write_all(&response).await?;
+            return Ok(());
+        }
+    };
+
+    debug!("Streaming logs of command {}", request.command_id);
+
+    // Subscribe before reading stored lines, so lines stored in between aren't missed.
+    // They'll be received twice, which is why we keep track of the highest id sent.
+    let mut receiver = state.log_writer.subscribe();
+
+    let (mut last_id, mut max_id) = match request.after_id {
+        Some(after_id) => {
+            let mut max_id = after_id;
+            let last_id = send_stored_lines(
+                &state.client,
+                &mut writer,
+                request.command_id,
+                after_id,
+                &mut max_id,
+            )
+            .await?;
+
+            (last_id, max_id)
+        }
+        None => get_last_ids(&state.client, request.command_id).await?,
+    };
+
+    loop {
+        select! {
+            received = receiver.recv() => match received {
+                Ok(log_line) => {
+                    if log_line.command_id == request.command_id && log_line.id > max_id {
+                        write_log_line(&mut writer, &log_line).await?;
+                        last_id = log_line.id;
+                        max_id = log_line.id;
+                    }
+                }
+                // We fell behind and missed some lines, but they're all in the database
+                Err(RecvError::Lagged(_)) => {
+                    last_id = send_stored_lines(
+                        &state.client,
+                        &mut writer,
+                        request.command_id,
+                        last_id,
+                        &mut max_id,
+                    )
+                    .await?;
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            // Nothing else is expected from the client, this is only to notice when it's gone,
+            // instead of on the next write. Clients that send their request from a pipe have to
+            // keep their end open, like socat's `ignoreeof` does.
+            line = lines.next_line() => match line {
+                Ok(Some(_)) => {}
+                Ok(None) | Err(_) => return Ok(()),
+            },
+        }
+    }
+}
+
+pub async fn serve(app: AppHandle) -> Result<(), AppCommandError> {
+    let path = Path::new(LOG_STREAM_SOCKET_FILE_NAME);
+
+    remove_stale_socket(path).await?;
+
+    let listener = UnixListener::bind(path)?;
+
+    // The logs can contain anything the commands print, like tokens
+    fs::set_permissions(path, Permissions::from_mode(0o600))?;
+
+    info!("Streaming logs on {}", path.to_string_lossy());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let app = app.clone();
+
+        spawn(async move {
+            if let Err(err) = handle_connection(app, stream).await {
+                error!(
+                    "Error in log stream connection: {}",
+                    serde_json::to_string(&err).unwrap_or_default()
+                );
+            }
+        });
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use tokio::sync::{broadcast, Mutex as AsyncMutex};
+
+use crate::{
+    errors::AppCommandError,
+    prisma::{_prisma::PrismaClient, command, command_log_line},
+    process::CommandLogLineSource,
+    run_clock::{load_latest_run, RunClock},
+};
+
+// Slow subscribers that fall further behind than this will miss lines, and have to catch up
+// from the database
+const BROADCAST_CAPACITY: usize = 1024;
+
+// None until the first line of the command since the app started, or the start of a run
+type CommandClock = Arc<AsyncMutex<Option<RunClock>>>;
+
+// Stores log lines, and lets other parts of the app know about every line that was stored
+#[derive(Clone)]
+pub struct LogWriter {
+    db_client: Arc<PrismaClient>,
+    sender: broadcast::Sender<command_log_line::Data>,
+    // The clock of a command is held while one of its lines is stamped and stored, so the ids of
+    // the lines are in the same order as their run and sequence, and can be used as cursors
+    run_clocks: Arc<Mutex<HashMap<i32, CommandClock>>>,
+}
+
+impl LogWriter {
+    pub fn new(db_client: Arc<PrismaClient>) -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+
+        Self {
+            db_client,
+            sender,
+            run_clocks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn command_clock(&self, command_id: i32) -> Result<CommandClock, AppCommandError> {
+        Ok(Arc::clone(
+            self.run_clocks.lock()?.entry(command_id).or_default(),
+        ))
+    }
+
+    // Lines written after this are part of a new run, which starts its sequence from 0
+    pub async fn start_run(&self, command_id: i32) -> Result<(), AppCommandError> {
+        let command_clock = self.command_clock(command_id)?;
+        let mut clock = command_clock.lock().await;
+
+        let current_run = match clock.as_ref() {
+            Some(clock) => clock.run,
+            None => load_latest_run(&self.db_client, command_id).await?.0,
+        };
+
+        *clock = Some(RunClock::new(current_run + 1, 0)?);
+
+        Ok(())
+    }
+
+    pub async fn write(
+        &self,
+        command_id: i32,
+        source: CommandLogLineSource,
+        line: String,
+        params: Vec<command_log_line::SetParam>,
+    ) -> Result<command_log_line::Data, AppCommandError> {
+        self.write_arrived(command_id, Instant::now(), source, line, params)
+            .await
+    }
+
+    // For lines that were held back after they arrived, they're timed by when they arrived but
+    // ordered by when they're written
+    pub async fn write_arrived(
+        &self,
+        command_id: i32,
+        arrived_at: Instant,
+        source: CommandLogLineSource,
+        line: String,
+        mut params: Vec<command_log_line::SetParam>,
+    ) -> Result<command_log_line::Data, AppCommandError> {
+        let command_clock = self.command_clock(command_id)?;
+        let mut clock = command_clock.lock().await;
+
+        let stamp = match clock.as_mut() {
+            Some(clock) => clock.tick_at(arrived_at),
+            // Continues where the stored lines left off, for when the app was restarted
+            None => {
+                let (run, next_sequence) = load_latest_run(&self.db_client, command_id).await?;
+                let mut new_clock = RunClock::new(run, next_sequence)?;
+                let stamp = new_clock.tick_at(arrived_at);
+                *clock = Some(new_clock);
+                stamp
+            }
+        };
+
+        params.extend([
+            command_log_line::run::set(stamp.run),
+            command_log_line::sequence::set(stamp.sequence),
+        ]);
+
+        let log_line = self
+            .db_client
+            .command_log_line()
+            .create(
+                command::id::equals(command_id),
+                source as i32,
+                line,
+                stamp.timestamp,
+                params,
+            )
+            .exec()
+            .await?;
+
+        // Still holding the clock, so subscribers get the lines in order too. Only fails when
+        // nobody is subscribed.
+        let _ = self.sender.send(log_line.clone());
+
+        Ok(log_line)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<command_log_line::Data> {
+        self.sender.subscribe()
+    }
+}
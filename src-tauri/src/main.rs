@@ -4,31 +4,74 @@
 #[allow(warnings, unused)]
 mod prisma;
 
+mod command_config;
+mod console_window;
+#[cfg(target_family = "unix")]
+mod control;
+mod environment;
 mod errors;
 mod events;
+mod log_export;
+mod log_level;
+mod log_parser;
+#[cfg(target_family = "unix")]
+mod log_stream;
+mod log_writer;
+mod maintenance;
+mod merged_logs;
+mod multiline;
+mod notifications;
 mod process;
+mod project_config;
+mod run_clock;
+mod script_import;
+mod shell;
+#[cfg(test)]
+mod test_utils;
+mod tray;
+mod triggers;
 mod utils;
 
 use std::{path::MAIN_SEPARATOR, sync::Arc, vec};
 
+use command_config::{CommandConfigFormat, ImportMergeStrategy};
+use console_window::ConsoleWindowGeometry;
+use environment::{load_shell_environment, ShellEnvironment};
 use errors::{AppCommandError, ClientError};
-use events::{send_command_update_event, AppEventPayload};
+use events::{send_command_update_event, AppEventPayload, EventSubscriptions};
+use log::error;
+use log_export::{LogExportFormat, LogExportRange};
+use log_level::LogLevel;
+use log_writer::LogWriter;
+use maintenance::DatabaseStats;
+use merged_logs::{MergedLogCursor, MergedLogLine};
+use notifications::NotificationRuleKind;
 use prisma::*;
 use tokio::join;
 use utils::{get_midpoint_string, trace_elapsed_time};
 
 use prisma_client_rust::{Direction, QueryError};
 use process::{ProcessManager, ProcessStatus};
+use project_config::ProjectConfigWatcher;
+use script_import::ScriptImportSource;
 use serde::Serialize;
 use specta::{collect_types, Type};
-use tauri::{api::path::home_dir, generate_handler, AppHandle, LogicalSize, Manager, Size, Window};
+use tauri::{
+    api::path::home_dir, generate_handler, AppHandle, LogicalSize, Manager, Size, State, Window,
+    WindowEvent,
+};
 use tauri_specta::ts;
+use tray::TrayMenuUpdater;
+use triggers::TriggerActionKind;
 
 type AppState<'a> = tauri::State<'a, AppStateData>;
 
 struct AppStateData {
     client: Arc<PrismaClient>,
     process_manager: Arc<ProcessManager>,
+    log_writer: LogWriter,
+    shell_environment: ShellEnvironment,
+    project_config_watcher: Arc<ProjectConfigWatcher>,
 }
 
 #[tauri::command]
@@ -59,18 +102,36 @@ async fn get_command(
     .await
 }
 
+// Lines without a detected level are left out when filtering by level
+fn log_line_filters(
+    command_id: i32,
+    min_level: Option<LogLevel>,
+) -> Vec<command_log_line::WhereParam> {
+    let mut filters = vec![command_log_line::command_id::equals(command_id)];
+
+    if let Some(min_level) = min_level {
+        filters.push(command_log_line::level::in_vec(min_level.at_or_above()));
+    }
+
+    filters
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn get_command_log_lines(
     state: AppState<'_>,
     command_id: i32,
+    min_level: Option<LogLevel>,
 ) -> Result<Vec<command_log_line::Data>, QueryError> {
     trace_elapsed_time("get_command_log_lines", || async {
+        // In the order the lines arrived, the id only orders lines stored before runs had sequences
         let mut log_lines = state
             .client
             .command_log_line()
-            .find_many(vec![command_log_line::command_id::equals(command_id)])
-            .order_by(command_log_line::timestamp::order(Direction::Desc))
+            .find_many(log_line_filters(command_id, min_level))
+            .order_by(command_log_line::run::order(Direction::Desc))
+            .order_by(command_log_line::sequence::order(Direction::Desc))
+            .order_by(command_log_line::id::order(Direction::Desc))
             .take(100)
             .exec()
             .await?;
@@ -88,17 +149,21 @@ async fn get_older_command_log_lines(
     state: AppState<'_>,
     command_id: i32,
     first_id: i32,
+    min_level: Option<LogLevel>,
 ) -> Result<Vec<command_log_line::Data>, AppCommandError> {
     trace_elapsed_time("get_older_command_log_lines", || async {
         if first_id == 0 {
             return Err(AppCommandError::ClientError(ClientError::InvalidCommandId));
         }
 
+        // In the order the lines arrived, the id only orders lines stored before runs had sequences
         let mut log_lines = state
             .client
             .command_log_line()
-            .find_many(vec![command_log_line::command_id::equals(command_id)])
-            .order_by(command_log_line::timestamp::order(Direction::Desc))
+            .find_many(log_line_filters(command_id, min_level))
+            .order_by(command_log_line::run::order(Direction::Desc))
+            .order_by(command_log_line::sequence::order(Direction::Desc))
+            .order_by(command_log_line::id::order(Direction::Desc))
             .cursor(command_log_line::id::equals(first_id))
             .skip(1)
             .take(100)
@@ -118,17 +183,20 @@ async fn get_newer_command_log_lines(
     state: AppState<'_>,
     command_id: i32,
     last_id: i32,
+    min_level: Option<LogLevel>,
 ) -> Result<Vec<command_log_line::Data>, QueryError> {
     trace_elapsed_time("get_newer_command_log_lines", || async {
         if last_id == 0 {
-            return get_command_log_lines(state, command_id).await;
+            return get_command_log_lines(state, command_id, min_level).await;
         }
 
         state
             .client
             .command_log_line()
-            .find_many(vec![command_log_line::command_id::equals(command_id)])
-            .order_by(command_log_line::timestamp::order(Direction::Asc))
+            .find_many(log_line_filters(command_id, min_level))
+            .order_by(command_log_line::run::order(Direction::Asc))
+            .order_by(command_log_line::sequence::order(Direction::Asc))
+            .order_by(command_log_line::id::order(Direction::Asc))
             .cursor(command_log_line::id::equals(last_id))
             .skip(1)
             .take(10000)
@@ -138,10 +206,82 @@ async fn get_newer_command_log_lines(
     .await
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn get_merged_log_lines(
+    state: AppState<'_>,
+    command_ids: Vec<i32>,
+    cursor: Option<MergedLogCursor>,
+) -> Result<Vec<MergedLogLine>, AppCommandError> {
+    trace_elapsed_time("get_merged_log_lines", || {
+        merged_logs::get_merged_log_lines(&state.client, command_ids, cursor)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_database_stats(state: AppState<'_>) -> Result<DatabaseStats, AppCommandError> {
+    trace_elapsed_time("get_database_stats", || {
+        maintenance::get_database_stats(&state.client)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn clear_command_logs(
+    state: AppState<'_>,
+    app: AppHandle,
+    command_id: i32,
+) -> Result<u32, AppCommandError> {
+    maintenance::clear_command_logs(&state.client, &app, command_id).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn clear_all_logs(state: AppState<'_>, app: AppHandle) -> Result<u32, AppCommandError> {
+    maintenance::clear_all_logs(&state.client, &app).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn optimize_database(state: AppState<'_>) -> Result<DatabaseStats, AppCommandError> {
+    trace_elapsed_time("optimize_database", || {
+        maintenance::optimize_database(&state.client)
+    })
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn export_command_logs(
+    state: AppState<'_>,
+    command_id: i32,
+    format: LogExportFormat,
+    range: LogExportRange,
+    path: String,
+) -> Result<u32, AppCommandError> {
+    trace_elapsed_time("export_command_logs", || {
+        log_export::export_command_logs(&state.client, command_id, format, range, &path)
+    })
+    .await
+}
+
 command::partial_unchecked!(CommandUpdateData {
     name
     command
     cwd
+    shell
+    shell_path
+    shell_login
+    shell_interactive
+    env
+    log_parser
+    level_patterns
+    multiline_mode
+    multiline_pattern
+    merge_stderr
 });
 
 #[tauri::command]
@@ -152,6 +292,12 @@ async fn update_command(
     command_id: i32,
     data: CommandUpdateData,
 ) -> Result<command::Data, AppCommandError> {
+    if data.merge_stderr == Some(true)
+        && triggers::has_stderr_triggers(&state.client, command_id).await?
+    {
+        return Err(AppCommandError::ClientError(ClientError::StderrMerged));
+    }
+
     let result = state
         .client
         .command()
@@ -306,12 +452,122 @@ async fn move_command_between(
     Ok(result)
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn export_commands(
+    state: AppState<'_>,
+    path: String,
+    format: CommandConfigFormat,
+    project_root: Option<String>,
+) -> Result<u32, AppCommandError> {
+    command_config::export_commands(&state.client, &path, format, project_root.as_deref()).await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn import_commands(
+    state: AppState<'_>,
+    app: AppHandle,
+    path: String,
+    format: CommandConfigFormat,
+    project_root: Option<String>,
+    merge_strategy: ImportMergeStrategy,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    let result = command_config::import_commands(
+        &state.client,
+        &path,
+        format,
+        project_root.as_deref(),
+        merge_strategy,
+    )
+    .await?;
+
+    for command in result.iter() {
+        send_command_update_event(&app, command.id)?;
+    }
+
+    Ok(result)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn scan_project_directory(
+    state: AppState<'_>,
+    app: AppHandle,
+    directory: String,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    project_config::scan_project_directory(
+        &state.client,
+        &app,
+        &state.project_config_watcher,
+        &directory,
+    )
+    .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn import_scripts(
+    state: AppState<'_>,
+    app: AppHandle,
+    directory: String,
+    source: ScriptImportSource,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    let result = script_import::import_scripts(&state.client, &directory, source).await?;
+
+    for command in result.iter() {
+        send_command_update_event(&app, command.id)?;
+    }
+
+    Ok(result)
+}
+
 #[tauri::command]
 #[specta::specta]
 fn set_window_size(window: Window, width: f64, height: f64) -> Result<(), tauri::Error> {
     window.set_size(Size::Logical(LogicalSize { width, height }))
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn open_console_window(
+    window: Window,
+    state: AppState<'_>,
+    command_id: i32,
+) -> Result<(), AppCommandError> {
+    let command = state
+        .client
+        .command()
+        .find_unique(command::id::equals(command_id))
+        .exec()
+        .await?;
+
+    match command {
+        Some(command) => console_window::open_console_window(&window.app_handle(), &command),
+        None => Err(AppCommandError::ClientError(ClientError::CommandNotFound)),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+fn subscribe_to_command_logs(
+    window: Window,
+    subscriptions: State<'_, EventSubscriptions>,
+    command_id: i32,
+) -> Result<(), AppCommandError> {
+    subscriptions.subscribe(window.label(), command_id)
+}
+
+#[tauri::command]
+#[specta::specta]
+fn unsubscribe_from_command_logs(
+    window: Window,
+    subscriptions: State<'_, EventSubscriptions>,
+    command_id: i32,
+) -> Result<(), AppCommandError> {
+    subscriptions.unsubscribe(window.label(), command_id)
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn get_process_status(
@@ -340,12 +596,238 @@ async fn run_process(state: AppState<'_>, command_id: i32) -> Result<(), AppComm
     }
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn restart_process(state: AppState<'_>, command_id: i32) -> Result<(), AppCommandError> {
+    let command = state
+        .client
+        .command()
+        .find_unique(command::id::equals(command_id))
+        .exec()
+        .await?;
+
+    match command {
+        Some(command) => {
+            state.process_manager.restart_process(command).await?;
+            Ok(())
+        }
+        None => Err(AppCommandError::ClientError(ClientError::CommandNotFound)),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn scale_process(
+    state: AppState<'_>,
+    command_id: i32,
+    instance_count: u32,
+) -> Result<(), AppCommandError> {
+    let command = state
+        .client
+        .command()
+        .find_unique(command::id::equals(command_id))
+        .exec()
+        .await?;
+
+    match command {
+        Some(command) => {
+            state
+                .process_manager
+                .scale_process(command, instance_count)
+                .await?;
+            Ok(())
+        }
+        None => Err(AppCommandError::ClientError(ClientError::CommandNotFound)),
+    }
+}
+
 #[tauri::command]
 #[specta::specta]
 async fn kill_process(state: AppState<'_>, command_id: i32) -> Result<(), AppCommandError> {
     state.process_manager.kill_process(command_id).await
 }
 
+#[tauri::command]
+#[specta::specta]
+async fn get_notification_rules(
+    state: AppState<'_>,
+    command_id: i32,
+) -> Result<Vec<command_notification_rule::Data>, QueryError> {
+    state
+        .client
+        .command_notification_rule()
+        .find_many(vec![command_notification_rule::command_id::equals(
+            command_id,
+        )])
+        .order_by(command_notification_rule::id::order(Direction::Asc))
+        .exec()
+        .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn create_notification_rule(
+    state: AppState<'_>,
+    command_id: i32,
+    kind: NotificationRuleKind,
+    pattern: Option<String>,
+) -> Result<command_notification_rule::Data, AppCommandError> {
+    let pattern = notifications::validate_rule(kind, pattern)?;
+
+    Ok(state
+        .client
+        .command_notification_rule()
+        .create(
+            command::id::equals(command_id),
+            kind.as_str().into(),
+            vec![command_notification_rule::pattern::set(pattern)],
+        )
+        .exec()
+        .await?)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn update_notification_rule(
+    state: AppState<'_>,
+    rule_id: i32,
+    kind: NotificationRuleKind,
+    pattern: Option<String>,
+) -> Result<command_notification_rule::Data, AppCommandError> {
+    let pattern = notifications::validate_rule(kind, pattern)?;
+
+    Ok(state
+        .client
+        .command_notification_rule()
+        .update(
+            command_notification_rule::id::equals(rule_id),
+            vec![
+                command_notification_rule::kind::set(kind.as_str().into()),
+                command_notification_rule::pattern::set(pattern),
+            ],
+        )
+        .exec()
+        .await?)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_notification_rule(
+    state: AppState<'_>,
+    rule_id: i32,
+) -> Result<(), AppCommandError> {
+    state
+        .client
+        .command_notification_rule()
+        .delete(command_notification_rule::id::equals(rule_id))
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn get_triggers(
+    state: AppState<'_>,
+    command_id: i32,
+) -> Result<Vec<command_trigger::Data>, QueryError> {
+    state
+        .client
+        .command_trigger()
+        .find_many(vec![command_trigger::command_id::equals(command_id)])
+        .order_by(command_trigger::id::order(Direction::Asc))
+        .exec()
+        .await
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn create_trigger(
+    state: AppState<'_>,
+    command_id: i32,
+    pattern: String,
+    source: Option<i32>,
+    action: TriggerActionKind,
+    target_command_id: Option<i32>,
+) -> Result<command_trigger::Data, AppCommandError> {
+    let target_command_id = triggers::validate_trigger(&pattern, action, target_command_id)?;
+    triggers::validate_trigger_source(&state.client, command_id, source).await?;
+
+    Ok(state
+        .client
+        .command_trigger()
+        .create_unchecked(
+            command_id,
+            pattern,
+            action.as_str().into(),
+            vec![
+                command_trigger::source::set(source),
+                command_trigger::target_command_id::set(target_command_id),
+            ],
+        )
+        .exec()
+        .await?)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn update_trigger(
+    state: AppState<'_>,
+    trigger_id: i32,
+    pattern: String,
+    source: Option<i32>,
+    action: TriggerActionKind,
+    target_command_id: Option<i32>,
+) -> Result<command_trigger::Data, AppCommandError> {
+    let target_command_id = triggers::validate_trigger(&pattern, action, target_command_id)?;
+
+    // A trigger that doesn't exist fails the update below
+    if let Some(trigger) = state
+        .client
+        .command_trigger()
+        .find_unique(command_trigger::id::equals(trigger_id))
+        .exec()
+        .await?
+    {
+        triggers::validate_trigger_source(&state.client, trigger.command_id, source).await?;
+    }
+
+    Ok(state
+        .client
+        .command_trigger()
+        .update_unchecked(
+            command_trigger::id::equals(trigger_id),
+            vec![
+                command_trigger::pattern::set(pattern),
+                command_trigger::source::set(source),
+                command_trigger::action::set(action.as_str().into()),
+                command_trigger::target_command_id::set(target_command_id),
+            ],
+        )
+        .exec()
+        .await?)
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn delete_trigger(state: AppState<'_>, trigger_id: i32) -> Result<(), AppCommandError> {
+    state
+        .client
+        .command_trigger()
+        .delete(command_trigger::id::equals(trigger_id))
+        .exec()
+        .await?;
+
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+async fn refresh_shell_environment(state: AppState<'_>) -> Result<(), AppCommandError> {
+    environment::refresh_shell_environment(&state.shell_environment).await
+}
+
 #[derive(Type, Serialize)]
 struct PlatformDetails {
     path_separator: char,
@@ -367,27 +849,56 @@ fn export_types() {
             create_command,
             get_command,
             set_window_size,
+            open_console_window,
+            subscribe_to_command_logs,
+            unsubscribe_from_command_logs,
             update_command,
             move_command_between,
             delete_command,
+            export_commands,
+            import_commands,
+            scan_project_directory,
+            import_scripts,
             get_platform_details,
             get_command_log_lines,
             get_newer_command_log_lines,
+            get_merged_log_lines,
+            get_database_stats,
+            clear_command_logs,
+            clear_all_logs,
+            optimize_database,
             get_older_command_log_lines,
+            export_command_logs,
             get_process_status,
             run_process,
+            restart_process,
+            scale_process,
             kill_process,
+            get_notification_rules,
+            create_notification_rule,
+            update_notification_rule,
+            delete_notification_rule,
+            get_triggers,
+            create_trigger,
+            update_trigger,
+            delete_trigger,
+            refresh_shell_environment,
         ],
         "../src/lib/generated/bindings.ts",
     )
     .unwrap();
 
     // Export custom types
-    let types = vec![
-        specta::ts::export::<AppEventPayload>(&Default::default()).unwrap(),
-    ];
+    let types = vec![specta::ts::export::<AppEventPayload>(&Default::default()).unwrap()];
 
-    std::fs::write("../src/lib/generated/data.ts", types.join("\n")).unwrap();
+    // The payloads reference types that are only exported in the bindings
+    let imports = "import type { CommandLogLine, ProcessStatus } from './bindings';\n";
+
+    std::fs::write(
+        "../src/lib/generated/data.ts",
+        imports.to_owned() + &types.join("\n"),
+    )
+    .unwrap();
 }
 
 #[test]
@@ -439,6 +950,8 @@ async fn main() {
         .await
         .expect("Database migration should succeed");
 
+    let shell_environment = load_shell_environment();
+
     tauri::Builder::default()
         .setup(|app| {
             #[cfg(debug_assertions)]
@@ -450,34 +963,158 @@ async fn main() {
             }
 
             let client_arc = Arc::new(db_client);
+            let app_handle = Arc::new(app.app_handle());
+            let log_writer = LogWriter::new(Arc::clone(&client_arc));
+
+            let process_manager = ProcessManager::new(
+                Arc::clone(&app_handle),
+                Arc::clone(&client_arc),
+                log_writer.clone(),
+                Arc::clone(&shell_environment),
+            );
+
+            tauri::async_runtime::spawn(events::send_log_line_events(
+                Arc::clone(&app_handle),
+                log_writer.subscribe(),
+            ));
+
+            let project_config_watcher = Arc::new(
+                ProjectConfigWatcher::new(Arc::clone(&client_arc), Arc::clone(&app_handle))
+                    .expect("Project config watcher should be available"),
+            );
 
-            let process_manager =
-                ProcessManager::new(Arc::new(app.app_handle()), Arc::clone(&client_arc));
+            {
+                let client = Arc::clone(&client_arc);
+                let watcher = Arc::clone(&project_config_watcher);
+                tauri::async_runtime::spawn(async move {
+                    if let Err(err) =
+                        project_config::resume_project_configs(&client, &app_handle, &watcher).await
+                    {
+                        error!(
+                            "Failed to sync project configs: {}",
+                            serde_json::to_string(&err).unwrap_or_default()
+                        );
+                    }
+                });
+            }
 
             let state = AppStateData {
                 client: client_arc,
-                process_manager: Arc::new(process_manager),
+                process_manager,
+                log_writer,
+                shell_environment,
+                project_config_watcher,
             };
 
             app.manage(state);
+            app.manage(EventSubscriptions::default());
+            app.manage(ConsoleWindowGeometry::default());
+            app.manage(TrayMenuUpdater::new(app.app_handle()));
+
+            #[cfg(target_family = "unix")]
+            {
+                let app_handle = app.app_handle();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(err) = control::serve(app_handle).await {
+                        error!(
+                            "Control socket stopped: {}",
+                            serde_json::to_string(&err).unwrap_or_default()
+                        );
+                    }
+                });
+
+                let app_handle = app.app_handle();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(err) = log_stream::serve(app_handle).await {
+                        error!(
+                            "Log stream socket stopped: {}",
+                            serde_json::to_string(&err).unwrap_or_default()
+                        );
+                    }
+                });
+            }
 
             Ok(())
         })
+        .system_tray(tray::create_system_tray())
+        .on_system_tray_event(tray::handle_tray_event)
+        .on_window_event(|event| {
+            let window = event.window();
+
+            // Running commands should keep running, so the main window only hides to the tray
+            if let WindowEvent::CloseRequested { api, .. } = event.event() {
+                if tray::is_main_window(window.label()) {
+                    api.prevent_close();
+                    if let Err(err) = window.hide() {
+                        error!("Failed to hide main window: {}", err);
+                    }
+                }
+            }
+
+            if let Some(state) = window.try_state::<AppStateData>() {
+                if let Err(err) = window.state::<ConsoleWindowGeometry>().handle_window_event(
+                    window,
+                    event.event(),
+                    Arc::clone(&state.client),
+                ) {
+                    error!(
+                        "Failed to handle console window event: {}",
+                        serde_json::to_string(&err).unwrap_or_default()
+                    );
+                }
+            }
+
+            if let WindowEvent::Destroyed = event.event() {
+                if let Err(err) = window
+                    .state::<EventSubscriptions>()
+                    .remove_window(window.label())
+                {
+                    error!(
+                        "Failed to remove event subscriptions: {}",
+                        serde_json::to_string(&err).unwrap_or_default()
+                    );
+                }
+            }
+        })
         .invoke_handler(generate_handler![
             get_commands,
             create_command,
             get_command,
             set_window_size,
+            open_console_window,
+            subscribe_to_command_logs,
+            unsubscribe_from_command_logs,
             update_command,
             move_command_between,
             delete_command,
+            export_commands,
+            import_commands,
+            scan_project_directory,
+            import_scripts,
             get_platform_details,
             get_command_log_lines,
             get_newer_command_log_lines,
+            get_merged_log_lines,
+            get_database_stats,
+            clear_command_logs,
+            clear_all_logs,
+            optimize_database,
             get_older_command_log_lines,
+            export_command_logs,
             get_process_status,
             run_process,
+            restart_process,
+            scale_process,
             kill_process,
+            get_notification_rules,
+            create_notification_rule,
+            update_notification_rule,
+            delete_notification_rule,
+            get_triggers,
+            create_trigger,
+            update_trigger,
+            delete_trigger,
+            refresh_shell_environment,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
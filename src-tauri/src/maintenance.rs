@@ -0,0 +1,246 @@
+use std::fs;
+
+use prisma_client_rust::raw;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::{AppHandle, Runtime};
+
+use crate::{
+    errors::AppCommandError,
+    events::send_command_logs_cleared_event,
+    prisma::{_prisma::PrismaClient, command_log_line},
+};
+
+// Relative to the data dir, like the database URL. SQLite keeps recent writes in the WAL and
+// journal files until they're moved into the main one, so they count too.
+const DATABASE_FILES: [&str; 3] = ["app.db", "app.db-wal", "app.db-journal"];
+
+#[derive(Debug, Serialize, Type)]
+pub struct CommandLogStats {
+    pub command_id: i32,
+    pub log_line_count: u32,
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct DatabaseStats {
+    // A float, as the frontend can't take 64 bit integers
+    pub size_bytes: f64,
+    pub commands: Vec<CommandLogStats>,
+}
+
+#[derive(Deserialize)]
+struct LogLineCountRow {
+    #[serde(rename = "commandId")]
+    command_id: i32,
+    // COUNT is a BigInt, which can come as a string
+    count: serde_json::Value,
+}
+
+impl LogLineCountRow {
+    fn count(&self) -> u32 {
+        self.count
+            .as_u64()
+            .or_else(|| self.count.as_str()?.parse().ok())
+            .unwrap_or_default() as u32
+    }
+}
+
+fn database_size() -> u64 {
+    DATABASE_FILES
+        .iter()
+        .filter_map(|path| fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+async fn get_command_ids(db_client: &PrismaClient) -> Result<Vec<i32>, AppCommandError> {
+    Ok(db_client
+        .command()
+        .find_many(vec![])
+        .exec()
+        .await?
+        .into_iter()
+        .map(|command| command.id)
+        .collect())
+}
+
+pub async fn get_database_stats(
+    db_client: &PrismaClient,
+) -> Result<DatabaseStats, AppCommandError> {
+    // Commands without lines aren't in the counts
+    let counts: Vec<LogLineCountRow> = db_client
+        ._query_raw(raw!(
+            "SELECT commandId, COUNT(*) AS count FROM CommandLogLine GROUP BY commandId"
+        ))
+        .exec()
+        .await?;
+
+    let commands = get_command_ids(db_client)
+        .await?
+        .into_iter()
+        .map(|command_id| CommandLogStats {
+            command_id,
+            log_line_count: counts
+                .iter()
+                .find(|row| row.command_id == command_id)
+                .map_or(0, LogLineCountRow::count),
+        })
+        .collect();
+
+    Ok(DatabaseStats {
+        size_bytes: database_size() as f64,
+        commands,
+    })
+}
+
+// Returns the number of deleted lines
+pub async fn clear_command_logs<R: Runtime>(
+    db_client: &PrismaClient,
+    app: &AppHandle<R>,
+    command_id: i32,
+) -> Result<u32, AppCommandError> {
+    let count = db_client
+        .command_log_line()
+        .delete_many(vec![command_log_line::command_id::equals(command_id)])
+        .exec()
+        .await?;
+
+    send_command_logs_cleared_event(app, command_id)?;
+
+    Ok(count as u32)
+}
+
+// Returns the number of deleted lines
+pub async fn clear_all_logs<R: Runtime>(
+    db_client: &PrismaClient,
+    app: &AppHandle<R>,
+) -> Result<u32, AppCommandError> {
+    let count = db_client
+        .command_log_line()
+        .delete_many(vec![])
+        .exec()
+        .await?;
+
+    for command_id in get_command_ids(db_client).await? {
+        send_command_logs_cleared_event(app, command_id)?;
+    }
+
+    Ok(count as u32)
+}
+
+// Clearing logs leaves the space in the file for reuse, VACUUM gives it back to the system.
+// ANALYZE updates the statistics the query planner uses to pick indices.
+pub async fn optimize_database(db_client: &PrismaClient) -> Result<DatabaseStats, AppCommandError> {
+    db_client._execute_raw(raw!("VACUUM")).exec().await?;
+    db_client._execute_raw(raw!("ANALYZE")).exec().await?;
+
+    get_database_stats(db_client).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use tauri::test::mock_app;
+
+    use super::*;
+    use crate::{prisma::command, process::CommandLogLineSource, test_utils::TestDatabase};
+
+    // Returns the id of a new command with this many lines
+    async fn create_command(db: &TestDatabase, name: &str, line_count: usize) -> i32 {
+        let command_id = db
+            .command()
+            .create(
+                name.into(),
+                temp_dir().to_string_lossy().into(),
+                "true".into(),
+                name.into(),
+                vec![],
+            )
+            .exec()
+            .await
+            .unwrap()
+            .id;
+
+        for index in 0..line_count {
+            db.command_log_line()
+                .create(
+                    command::id::equals(command_id),
+                    CommandLogLineSource::STDOUT as i32,
+                    format!("line {}", index),
+                    index as f64,
+                    vec![],
+                )
+                .exec()
+                .await
+                .unwrap();
+        }
+
+        command_id
+    }
+
+    fn line_counts(stats: &DatabaseStats) -> Vec<(i32, u32)> {
+        stats
+            .commands
+            .iter()
+            .map(|c| (c.command_id, c.log_line_count))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn counts_lines_of_every_command() {
+        let db = TestDatabase::new().await;
+        let first = create_command(&db, "a", 3).await;
+        let second = create_command(&db, "b", 0).await;
+
+        let stats = get_database_stats(&db).await.unwrap();
+
+        assert_eq!(line_counts(&stats), vec![(first, 3), (second, 0)]);
+    }
+
+    #[tokio::test]
+    async fn clears_logs_of_one_command() {
+        let db = TestDatabase::new().await;
+        let app = mock_app();
+        let first = create_command(&db, "a", 3).await;
+        let second = create_command(&db, "b", 2).await;
+
+        assert_eq!(
+            clear_command_logs(&db, &app.handle(), first).await.unwrap(),
+            3
+        );
+
+        let stats = get_database_stats(&db).await.unwrap();
+        assert_eq!(line_counts(&stats), vec![(first, 0), (second, 2)]);
+    }
+
+    #[tokio::test]
+    async fn clears_logs_of_all_commands() {
+        let db = TestDatabase::new().await;
+        let app = mock_app();
+        let first = create_command(&db, "a", 3).await;
+        let second = create_command(&db, "b", 2).await;
+
+        assert_eq!(clear_all_logs(&db, &app.handle()).await.unwrap(), 5);
+
+        let stats = get_database_stats(&db).await.unwrap();
+        assert_eq!(line_counts(&stats), vec![(first, 0), (second, 0)]);
+    }
+
+    #[tokio::test]
+    async fn optimizing_keeps_the_lines() {
+        let db = TestDatabase::new().await;
+        let app = mock_app();
+        let first = create_command(&db, "a", 50).await;
+        let second = create_command(&db, "b", 20).await;
+
+        clear_command_logs(&db, &app.handle(), first).await.unwrap();
+
+        let stats = optimize_database(&db).await.unwrap();
+        assert_eq!(line_counts(&stats), vec![(first, 0), (second, 20)]);
+        assert_eq!(
+            db.command_log_line().count(vec![]).exec().await.unwrap(),
+            20
+        );
+    }
+}
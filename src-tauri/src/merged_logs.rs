@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use prisma_client_rust::{
+    operator::{and, or},
+    Direction,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::{
+    errors::AppCommandError,
+    prisma::{_prisma::PrismaClient, command, command_log_line},
+};
+
+// Same page sizes as the log lines of a single command
+const PAGE_SIZE: i64 = 100;
+const NEWER_PAGE_SIZE: i64 = 10000;
+
+// Lines are ordered by time, and by id when they share a timestamp. The cursor holds both, so
+// it doesn't matter whether its line still exists.
+#[derive(Debug, Deserialize, Type, Clone, Copy)]
+pub enum MergedLogCursor {
+    // The page before this line
+    Before { timestamp: f64, id: i32 },
+    // All lines after this one, up to the newer page size
+    After { timestamp: f64, id: i32 },
+}
+
+#[derive(Debug, Serialize, Type)]
+pub struct MergedLogLine {
+    pub command_id: i32,
+    // The name of the command, or the command itself when it has no name
+    pub command_name: String,
+    pub log_line: command_log_line::Data,
+}
+
+// Lines of several commands interleaved by time, the latest page when there's no cursor
+pub async fn get_merged_log_lines(
+    db_client: &PrismaClient,
+    mut command_ids: Vec<i32>,
+    cursor: Option<MergedLogCursor>,
+) -> Result<Vec<MergedLogLine>, AppCommandError> {
+    // Every command is queried on its own, once is enough
+    command_ids.sort_unstable();
+    command_ids.dedup();
+
+    if command_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let command_names: HashMap<i32, String> = db_client
+        .command()
+        .find_many(vec![command::id::in_vec(command_ids.clone())])
+        .exec()
+        .await?
+        .into_iter()
+        .map(|command| {
+            let name = if command.name.is_empty() {
+                command.command
+            } else {
+                command.name
+            };
+
+            (command.id, name)
+        })
+        .collect();
+
+    let page_size = match cursor {
+        Some(MergedLogCursor::After { .. }) => NEWER_PAGE_SIZE,
+        _ => PAGE_SIZE,
+    };
+
+    let log_lines = load_page(db_client, &command_ids, cursor, page_size).await?;
+
+    Ok(log_lines
+        .into_iter()
+        .map(|log_line| MergedLogLine {
+            command_id: log_line.command_id,
+            command_name: command_names
+                .get(&log_line.command_id)
+                .cloned()
+                .unwrap_or_default(),
+            log_line,
+        })
+        .collect())
+}
+
+// Each command is queried on its own, so the (commandId, timestamp) index can be used, and the
+// pages are merged here
+async fn load_page(
+    db_client: &PrismaClient,
+    command_ids: &[i32],
+    cursor: Option<MergedLogCursor>,
+    page_size: i64,
+) -> Result<Vec<command_log_line::Data>, AppCommandError> {
+    let direction = match cursor {
+        Some(MergedLogCursor::After { .. }) => Direction::Asc,
+        _ => Direction::Desc,
+    };
+
+    let mut log_lines = vec![];
+
+    for command_id in command_ids {
+        let mut filters = vec![command_log_line::command_id::equals(*command_id)];
+
+        match cursor {
+            Some(MergedLogCursor::Before { timestamp, id }) => filters.push(or(vec![
+                command_log_line::timestamp::lt(timestamp),
+                and(vec![
+                    command_log_line::timestamp::equals(timestamp),
+                    command_log_line::id::lt(id),
+                ]),
+            ])),
+            Some(MergedLogCursor::After { timestamp, id }) => filters.push(or(vec![
+                command_log_line::timestamp::gt(timestamp),
+                and(vec![
+                    command_log_line::timestamp::equals(timestamp),
+                    command_log_line::id::gt(id),
+                ]),
+            ])),
+            None => {}
+        }
+
+        log_lines.extend(
+            db_client
+                .command_log_line()
+                .find_many(filters)
+                .order_by(command_log_line::timestamp::order(direction))
+                .order_by(command_log_line::id::order(direction))
+                .take(page_size)
+                .exec()
+                .await?,
+        );
+    }
+
+    log_lines.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+    // Pages before the cursor end at it, so the page is the end of the sorted lines
+    if matches!(direction, Direction::Desc) {
+        let start = log_lines.len().saturating_sub(page_size as usize);
+        log_lines.drain(..start);
+    } else {
+        log_lines.truncate(page_size as usize);
+    }
+
+    Ok(log_lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use super::*;
+    use crate::{process::CommandLogLineSource, test_utils::TestDatabase};
+
+    async fn create_command(db: &TestDatabase, name: &str) -> i32 {
+        db.command()
+            .create(
+                name.into(),
+                temp_dir().to_string_lossy().into(),
+                "true".into(),
+                name.into(),
+                vec![],
+            )
+            .exec()
+            .await
+            .unwrap()
+            .id
+    }
+
+    // Two commands whose lines interleave, some of them at the same time. Returns the ids in the
+    // merged order.
+    async fn insert_lines(db: &TestDatabase) -> (Vec<i32>, Vec<i32>) {
+        let first = create_command(db, "a").await;
+        let second = create_command(db, "b").await;
+
+        let mut log_lines = vec![];
+
+        for (command_id, timestamp) in [
+            (first, 1.0),
+            (second, 4.0),
+            (first, 2.0),
+            (second, 2.0),
+            (first, 5.0),
+            (second, 3.0),
+            (second, 6.0),
+            (first, 6.0),
+            (first, 7.0),
+        ] {
+            let log_line = db
+                .command_log_line()
+                .create(
+                    command::id::equals(command_id),
+                    CommandLogLineSource::STDOUT as i32,
+                    format!("{} at {}", command_id, timestamp),
+                    timestamp,
+                    vec![],
+                )
+                .exec()
+                .await
+                .unwrap();
+
+            log_lines.push(log_line);
+        }
+
+        log_lines.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp).then(a.id.cmp(&b.id)));
+
+        (
+            vec![first, second],
+            log_lines.into_iter().map(|l| l.id).collect(),
+        )
+    }
+
+    fn ids(log_lines: &[command_log_line::Data]) -> Vec<i32> {
+        log_lines.iter().map(|l| l.id).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_backwards_through_every_line() {
+        let db = TestDatabase::new().await;
+        let (command_ids, expected) = insert_lines(&db).await;
+
+        let mut page = load_page(&db, &command_ids, None, 2).await.unwrap();
+        let mut all = vec![];
+
+        while !page.is_empty() {
+            all.splice(0..0, ids(&page));
+
+            let cursor = MergedLogCursor::Before {
+                timestamp: page[0].timestamp,
+                id: page[0].id,
+            };
+            page = load_page(&db, &command_ids, Some(cursor), 2).await.unwrap();
+        }
+
+        assert_eq!(all, expected);
+    }
+
+    #[tokio::test]
+    async fn pages_forwards_through_every_line() {
+        let db = TestDatabase::new().await;
+        let (command_ids, expected) = insert_lines(&db).await;
+
+        let mut cursor = MergedLogCursor::After {
+            timestamp: 0.0,
+            id: 0,
+        };
+        let mut all = vec![];
+
+        loop {
+            let page = load_page(&db, &command_ids, Some(cursor), 2).await.unwrap();
+
+            let Some(last) = page.last() else {
+                break;
+            };
+
+            cursor = MergedLogCursor::After {
+                timestamp: last.timestamp,
+                id: last.id,
+            };
+            all.extend(ids(&page));
+        }
+
+        assert_eq!(all, expected);
+    }
+
+    #[tokio::test]
+    async fn cursor_line_can_be_deleted() {
+        let db = TestDatabase::new().await;
+        let (command_ids, expected) = insert_lines(&db).await;
+
+        let cursor_line = db
+            .command_log_line()
+            .delete(command_log_line::id::equals(expected[4]))
+            .exec()
+            .await
+            .unwrap();
+
+        let merged = get_merged_log_lines(
+            &db,
+            command_ids,
+            Some(MergedLogCursor::Before {
+                timestamp: cursor_line.timestamp,
+                id: cursor_line.id,
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            merged.iter().map(|l| l.log_line.id).collect::<Vec<_>>(),
+            expected[..4]
+        );
+        assert_eq!(
+            merged
+                .iter()
+                .map(|l| l.command_name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "a", "b", "b"]
+        );
+    }
+}
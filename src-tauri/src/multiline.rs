@@ -0,0 +1,215 @@
+use std::time::Instant;
+
+use log::error;
+use regex::Regex;
+
+use crate::prisma::command;
+
+// Stops a runaway entry, e.g. when every line of the output is indented
+const MAX_LINES_PER_ENTRY: i32 = 500;
+
+#[derive(Clone)]
+pub enum MultilineRule {
+    // Lines starting with whitespace belong to the previous line
+    Indentation,
+    // Lines matching the pattern belong to the previous line
+    Pattern(Regex),
+}
+
+impl MultilineRule {
+    pub fn from_command(command: &command::Data) -> Option<Self> {
+        match command.multiline_mode.as_deref() {
+            Some("indentation") => Some(MultilineRule::Indentation),
+            Some("pattern") => {
+                let pattern = command
+                    .multiline_pattern
+                    .as_deref()
+                    .filter(|p| !p.is_empty())?;
+
+                match Regex::new(pattern) {
+                    Ok(regex) => Some(MultilineRule::Pattern(regex)),
+                    Err(err) => {
+                        error!("Ignoring invalid multiline pattern {}: {}", pattern, err);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn is_continuation(&self, line: &str) -> bool {
+        match self {
+            MultilineRule::Indentation => {
+                line.starts_with(char::is_whitespace) && !line.trim().is_empty()
+            }
+            MultilineRule::Pattern(regex) => regex.is_match(line),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LogEntry {
+    pub line: String,
+    pub line_count: i32,
+    // When the first line arrived, the entry is timed by it
+    pub arrived_at: Instant,
+}
+
+// Joins continuation lines into the entry they belong to. An entry is only complete once the next
+// one starts, so the reader has to call finish when the output goes quiet.
+pub struct LineJoiner {
+    rule: Option<MultilineRule>,
+    pending: Option<LogEntry>,
+}
+
+impl LineJoiner {
+    pub fn new(rule: Option<MultilineRule>) -> Self {
+        Self {
+            rule,
+            pending: None,
+        }
+    }
+
+    // Returns the previous entry if this line starts a new one
+    pub fn push(&mut self, line: String, arrived_at: Instant) -> Option<LogEntry> {
+        let Some(rule) = &self.rule else {
+            return Some(LogEntry {
+                line,
+                line_count: 1,
+                arrived_at,
+            });
+        };
+
+        if let Some(pending) = &mut self.pending {
+            if pending.line_count < MAX_LINES_PER_ENTRY && rule.is_continuation(&line) {
+                pending.line.push('\n');
+                pending.line.push_str(&line);
+                pending.line_count += 1;
+                return None;
+            }
+        }
+
+        self.pending.replace(LogEntry {
+            line,
+            line_count: 1,
+            arrived_at,
+        })
+    }
+
+    pub fn finish(&mut self) -> Option<LogEntry> {
+        self.pending.take()
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    // The nth line arrives n milliseconds after the start
+    fn arrival(start: Instant, index: u64) -> Instant {
+        start + Duration::from_millis(index)
+    }
+
+    fn push_all(joiner: &mut LineJoiner, start: Instant, lines: &[&str]) -> Vec<LogEntry> {
+        let mut entries: Vec<LogEntry> = lines
+            .iter()
+            .zip(0..)
+            .filter_map(|(line, index)| joiner.push(line.to_string(), arrival(start, index)))
+            .collect();
+        entries.extend(joiner.finish());
+        entries
+    }
+
+    #[test]
+    fn joins_indented_lines() {
+        let mut joiner = LineJoiner::new(Some(MultilineRule::Indentation));
+        let start = Instant::now();
+
+        let entries = push_all(
+            &mut joiner,
+            start,
+            &[
+                "Traceback (most recent call last):",
+                "  File \"app.py\", line 3, in <module>",
+                "    main()",
+                "ValueError: boom",
+                "",
+                "done",
+            ],
+        );
+
+        assert_eq!(
+            entries,
+            vec![
+                LogEntry {
+                    line: "Traceback (most recent call last):\n  File \"app.py\", line 3, in <module>\n    main()".into(),
+                    line_count: 3,
+                    arrived_at: arrival(start, 0),
+                },
+                LogEntry {
+                    line: "ValueError: boom".into(),
+                    line_count: 1,
+                    arrived_at: arrival(start, 3),
+                },
+                LogEntry {
+                    line: "".into(),
+                    line_count: 1,
+                    arrived_at: arrival(start, 4),
+                },
+                LogEntry {
+                    line: "done".into(),
+                    line_count: 1,
+                    arrived_at: arrival(start, 5),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn joins_lines_matching_pattern() {
+        let mut joiner = LineJoiner::new(Some(MultilineRule::Pattern(
+            Regex::new(r"^\s+at |^Caused by:").unwrap(),
+        )));
+        let start = Instant::now();
+
+        let entries = push_all(
+            &mut joiner,
+            start,
+            &[
+                "java.lang.IllegalStateException: oops",
+                "    at App.main(App.java:5)",
+                "Caused by: java.io.IOException",
+                "    at App.read(App.java:9)",
+                "started",
+            ],
+        );
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].line_count, 4);
+        assert_eq!(entries[1].line, "started");
+        assert_eq!(entries[1].arrived_at, arrival(start, 4));
+    }
+
+    #[test]
+    fn passes_lines_through_without_rule() {
+        let mut joiner = LineJoiner::new(None);
+        let now = Instant::now();
+
+        assert_eq!(
+            joiner.push("  indented".into(), now),
+            Some(LogEntry {
+                line: "  indented".into(),
+                line_count: 1,
+                arrived_at: now,
+            })
+        );
+        assert!(!joiner.has_pending());
+    }
+}
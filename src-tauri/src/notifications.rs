@@ -0,0 +1,244 @@
+use std::{process::ExitStatus, sync::Mutex, time::Instant};
+
+use log::error;
+use regex::Regex;
+use serde::Deserialize;
+use specta::Type;
+use tauri::{api::notification::Notification, AppHandle, Runtime};
+use tokio::time::Duration;
+
+use crate::{
+    errors::{AppCommandError, ClientError},
+    prisma::{_prisma::PrismaClient, command, command_notification_rule},
+};
+
+// A noisy command could match a pattern on every line, only notify once in this time
+const LINE_NOTIFICATION_COOLDOWN: Duration = Duration::from_secs(10);
+
+// Serialized the same way as it's stored in the DB
+#[derive(Debug, Deserialize, Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationRuleKind {
+    NonZeroExit,
+    AnyExit,
+    LinePattern,
+}
+
+impl NotificationRuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationRuleKind::NonZeroExit => "nonZeroExit",
+            NotificationRuleKind::AnyExit => "anyExit",
+            NotificationRuleKind::LinePattern => "linePattern",
+        }
+    }
+}
+
+// Checks that the rule can be used, and returns the pattern to store for it
+pub fn validate_rule(
+    kind: NotificationRuleKind,
+    pattern: Option<String>,
+) -> Result<Option<String>, AppCommandError> {
+    match kind {
+        NotificationRuleKind::LinePattern => {
+            // An empty pattern would match every line
+            let pattern = pattern
+                .filter(|pattern| !pattern.is_empty())
+                .ok_or(AppCommandError::ClientError(ClientError::MissingPattern))?;
+            Regex::new(&pattern)?;
+            Ok(Some(pattern))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn show_notification<R: Runtime>(app_handle: &AppHandle<R>, title: &str, body: &str) {
+    let result = Notification::new(&app_handle.config().tauri.bundle.identifier)
+        .title(title)
+        .body(body)
+        .show();
+
+    if let Err(err) = result {
+        error!("Failed to show notification: {}", err);
+    }
+}
+
+// The notification rules of a command, loaded when one of its instances starts
+pub struct CommandNotifier<R: Runtime> {
+    app_handle: AppHandle<R>,
+    title: String,
+    notify_on_exit: bool,
+    notify_on_failure: bool,
+    line_patterns: Vec<Regex>,
+    last_line_notification: Mutex<Option<Instant>>,
+}
+
+impl<R: Runtime> CommandNotifier<R> {
+    pub async fn load(
+        db_client: &PrismaClient,
+        app_handle: AppHandle<R>,
+        command: &command::Data,
+    ) -> Result<Self, AppCommandError> {
+        let rules = db_client
+            .command_notification_rule()
+            .find_many(vec![command_notification_rule::command_id::equals(
+                command.id,
+            )])
+            .exec()
+            .await?;
+
+        let has_rule = |kind: NotificationRuleKind| rules.iter().any(|r| r.kind == kind.as_str());
+
+        let line_patterns = rules
+            .iter()
+            .filter(|r| r.kind == NotificationRuleKind::LinePattern.as_str())
+            .filter_map(|r| r.pattern.as_deref())
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(err) => {
+                    error!("Skipping invalid notification pattern {}: {}", pattern, err);
+                    None
+                }
+            })
+            .collect();
+
+        let title = if command.name.is_empty() {
+            command.command.clone()
+        } else {
+            command.name.clone()
+        };
+
+        Ok(Self {
+            notify_on_exit: has_rule(NotificationRuleKind::AnyExit),
+            notify_on_failure: has_rule(NotificationRuleKind::NonZeroExit),
+            app_handle,
+            title,
+            line_patterns,
+            last_line_notification: Mutex::new(None),
+        })
+    }
+
+    pub fn on_line(&self, line: &str) {
+        if self.line_notification_due(line) {
+            show_notification(&self.app_handle, &self.title, line);
+        }
+    }
+
+    pub fn on_exit(&self, status: &ExitStatus) {
+        if self.exit_notification_due(status) {
+            show_notification(
+                &self.app_handle,
+                &self.title,
+                &format!("Command finished with {}", status),
+            );
+        }
+    }
+
+    // Starts the cooldown when it returns true
+    fn line_notification_due(&self, line: &str) -> bool {
+        if !self.line_patterns.iter().any(|p| p.is_match(line)) {
+            return false;
+        }
+
+        let Ok(mut last_line_notification) = self.last_line_notification.lock() else {
+            return false;
+        };
+
+        if let Some(last) = *last_line_notification {
+            if last.elapsed() < LINE_NOTIFICATION_COOLDOWN {
+                return false;
+            }
+        }
+
+        *last_line_notification = Some(Instant::now());
+        true
+    }
+
+    fn exit_notification_due(&self, status: &ExitStatus) -> bool {
+        self.notify_on_exit || (self.notify_on_failure && !status.success())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::test::{mock_app, MockRuntime};
+
+    use super::*;
+
+    fn notifier(
+        notify_on_exit: bool,
+        notify_on_failure: bool,
+        line_patterns: &[&str],
+    ) -> CommandNotifier<MockRuntime> {
+        CommandNotifier {
+            app_handle: mock_app().handle(),
+            title: "test".into(),
+            notify_on_exit,
+            notify_on_failure,
+            line_patterns: line_patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).unwrap())
+                .collect(),
+            last_line_notification: Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn validates_rules() {
+        assert_eq!(
+            validate_rule(NotificationRuleKind::LinePattern, Some("ERROR".into())).unwrap(),
+            Some("ERROR".into())
+        );
+        assert!(matches!(
+            validate_rule(NotificationRuleKind::LinePattern, Some("".into())),
+            Err(AppCommandError::ClientError(ClientError::MissingPattern))
+        ));
+        assert!(matches!(
+            validate_rule(NotificationRuleKind::LinePattern, None),
+            Err(AppCommandError::ClientError(ClientError::MissingPattern))
+        ));
+        assert!(matches!(
+            validate_rule(NotificationRuleKind::LinePattern, Some("(".into())),
+            Err(AppCommandError::PatternError(_))
+        ));
+
+        // Exit rules have no pattern
+        assert_eq!(
+            validate_rule(NotificationRuleKind::NonZeroExit, Some("ERROR".into())).unwrap(),
+            None
+        );
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn notifies_on_exit() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let success = ExitStatus::from_raw(0);
+        let failure = ExitStatus::from_raw(1 << 8);
+
+        assert!(!notifier(false, false, &[]).exit_notification_due(&failure));
+
+        assert!(!notifier(false, true, &[]).exit_notification_due(&success));
+        assert!(notifier(false, true, &[]).exit_notification_due(&failure));
+
+        assert!(notifier(true, false, &[]).exit_notification_due(&success));
+        assert!(notifier(true, false, &[]).exit_notification_due(&failure));
+    }
+
+    #[test]
+    fn line_notifications_cool_down() {
+        let notifier = notifier(false, false, &["ERROR"]);
+
+        assert!(!notifier.line_notification_due("all good"));
+        assert!(notifier.line_notification_due("ERROR: first"));
+        assert!(!notifier.line_notification_due("ERROR: right after"));
+
+        // Once the cooldown is over, the next match notifies again
+        *notifier.last_line_notification.lock().unwrap() =
+            Instant::now().checked_sub(LINE_NOTIFICATION_COOLDOWN);
+
+        assert!(notifier.line_notification_due("ERROR: later"));
+        assert!(!notifier.line_notification_due("ERROR: right after that"));
+    }
+}
@@ -1,7 +1,9 @@
 use std::{
+    collections::HashMap,
     future::Future,
-    sync::Arc,
-    time::{SystemTime, SystemTimeError},
+    process::ExitStatus,
+    sync::{Arc, Weak},
+    time::Instant,
 };
 
 #[cfg(target_family = "unix")]
@@ -11,37 +13,65 @@ use nix::{
 };
 
 #[cfg(target_family = "windows")]
-use async_process::windows::CommandExt;
+use async_process::{windows::CommandExt, Command};
 
 use serde::Serialize;
 use specta::Type;
-use tauri::AppHandle;
-use tokio::time::{sleep, Duration};
-use tokio::{select, sync::Mutex};
+use tauri::{AppHandle, Runtime, Wry};
+use tokio::time::{sleep, timeout, Duration};
+use tokio::{
+    select,
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        Mutex, OwnedMutexGuard,
+    },
+};
 
-use async_process::{Child, Command, Stdio};
-use futures_lite::{io::BufReader, AsyncBufReadExt, StreamExt};
+use async_process::{Child, Stdio};
+use blocking::Unblock;
+use futures_lite::{io::BufReader, AsyncBufReadExt, AsyncRead, StreamExt};
+use os_pipe::pipe;
 use tokio::{spawn, task::JoinHandle, try_join};
 
 use log::{debug, error, trace};
 
 use crate::{
-    errors::AppCommandError,
-    events::{send_command_log_update_event, send_command_update_event},
-    prisma::{_prisma::PrismaClient, command},
+    environment::ShellEnvironment,
+    errors::{AppCommandError, ClientError},
+    events::{send_command_update_event, send_process_status_event},
+    log_level::{LevelDetector, LogLevel},
+    log_parser::{parse_line, LogParserMode},
+    log_writer::LogWriter,
+    multiline::{LineJoiner, LogEntry, MultilineRule},
+    notifications::CommandNotifier,
+    prisma::{_prisma::PrismaClient, command, command_log_line},
+    shell::build_command,
+    triggers::{CommandTriggers, TriggerAction},
 };
 
-
-enum CommandLogLineSource {
+#[derive(Clone, Copy)]
+pub enum CommandLogLineSource {
     STDOUT = 1,
     STDERR = 2,
     INFO = 3,
 }
 
+impl CommandLogLineSource {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CommandLogLineSource::STDOUT => "stdout",
+            CommandLogLineSource::STDERR => "stderr",
+            CommandLogLineSource::INFO => "info",
+        }
+    }
+}
+
 enum LastRunResultType {
     Exit,
     Killed,
     Error,
+    // A trigger marked the run as failed, the exit code is still stored
+    Failed,
 }
 
 impl LastRunResultType {
@@ -50,21 +80,54 @@ impl LastRunResultType {
             LastRunResultType::Exit => "exit",
             LastRunResultType::Killed => "killed",
             LastRunResultType::Error => "error",
+            LastRunResultType::Failed => "failed",
         }
     }
 }
 
-fn timestamp() -> Result<f64, SystemTimeError> {
-    let nanos = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)?
-        .as_nanos();
-    Ok(nanos as f64 / 1000000.0)
+// Status changes that happen within this time are sent as a single event
+const STATUS_EVENT_DEBOUNCE: Duration = Duration::from_millis(50);
+
+// Error and warning lines of the current run, counted in memory and stored with the status events
+#[derive(Default)]
+struct LevelCounts {
+    errors: i32,
+    warnings: i32,
+    // Whether they changed since they were last stored
+    changed: bool,
+}
+
+// How the current run of a command went so far, stored when its last instance exits
+#[derive(Default)]
+struct RunResult {
+    // Set by a markFailed trigger on any of the instances
+    failed: bool,
+    // The exit status of the instance that exited worst, failures win over successes
+    worst_exit: Option<ExitStatus>,
+}
+
+impl RunResult {
+    fn record_exit(&mut self, status: ExitStatus) {
+        let is_worse = match self.worst_exit {
+            Some(worst) => worst.success() && !status.success(),
+            None => true,
+        };
+
+        if is_worse {
+            self.worst_exit = Some(status);
+        }
+    }
 }
 
+// How long the output has to be quiet before a pending multi-line entry is stored
+const MULTILINE_FLUSH_DELAY: Duration = Duration::from_millis(100);
+
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
-struct OngoingProcess {
-    command_id: i32,
+struct ProcessInstance {
+    // Passed to the process as INSTANCE_INDEX, and used to tag its log lines
+    index: i32,
+
     child: Arc<Mutex<Child>>,
 
     // The join handle of the task that waits for the process to finish
@@ -75,155 +138,257 @@ struct OngoingProcess {
     output_join_handle: Arc<Mutex<Option<JoinHandle<Result<(), AppCommandError>>>>>,
 }
 
-pub struct ProcessManager {
+struct OngoingProcess {
+    command_id: i32,
+
+    // Never empty, the whole entry is removed when the last instance stops
+    instances: Vec<ProcessInstance>,
+}
+
+pub struct ProcessManager<R: Runtime = Wry> {
     ongoing_processes: Arc<Mutex<Vec<OngoingProcess>>>,
     stopping_commands: Arc<Mutex<Vec<i32>>>,
-    app_handle: Arc<AppHandle>,
+
+    // Held while a command is being started, stopped or restarted, so these can't interleave
+    command_locks: Arc<Mutex<HashMap<i32, Arc<Mutex<()>>>>>,
+
+    level_counts: Arc<Mutex<HashMap<i32, LevelCounts>>>,
+
+    run_results: Arc<Mutex<HashMap<i32, RunResult>>>,
+
+    app_handle: Arc<AppHandle<R>>,
     db_client: Arc<PrismaClient>,
+    log_writer: LogWriter,
+    shell_environment: ShellEnvironment,
+
+    // Ids of commands whose status might have changed, a ProcessStatusEvent is sent for them
+    status_changes: UnboundedSender<i32>,
+
+    // Triggers run actions from the output readers, which need to call back into the manager
+    this: Weak<Self>,
 }
 
 #[derive(Debug, Serialize, Type, Clone, Copy)]
 pub enum ProcessStatus {
-    Running,
+    Running { instances: u32 },
     Stopping,
     Stopped,
 }
 
-impl ProcessManager {
-    pub fn new(app_handle: Arc<AppHandle>, db_client: Arc<PrismaClient>) -> Self {
-        Self {
-            ongoing_processes: Arc::new(Mutex::new(vec![])),
-            stopping_commands: Arc::new(Mutex::new(vec![])),
+impl<R: Runtime> ProcessManager<R> {
+    pub fn new(
+        app_handle: Arc<AppHandle<R>>,
+        db_client: Arc<PrismaClient>,
+        log_writer: LogWriter,
+        shell_environment: ShellEnvironment,
+    ) -> Arc<Self> {
+        let ongoing_processes = Arc::new(Mutex::new(vec![]));
+        let stopping_commands = Arc::new(Mutex::new(vec![]));
+        let level_counts = Arc::new(Mutex::new(HashMap::new()));
+        let (status_changes, status_change_receiver) = unbounded_channel();
+
+        spawn(send_status_events(
+            Arc::clone(&app_handle),
+            Arc::clone(&db_client),
+            Arc::clone(&ongoing_processes),
+            Arc::clone(&stopping_commands),
+            Arc::clone(&level_counts),
+            status_change_receiver,
+        ));
+
+        Arc::new_cyclic(|this| Self {
+            ongoing_processes,
+            stopping_commands,
+            command_locks: Arc::new(Mutex::new(HashMap::new())),
+            level_counts,
+            run_results: Arc::new(Mutex::new(HashMap::new())),
             app_handle,
             db_client,
-        }
+            log_writer,
+            shell_environment,
+            status_changes,
+            this: this.clone(),
+        })
     }
 
     pub async fn check_process_status(
         &self,
         command_id: i32,
     ) -> Result<ProcessStatus, AppCommandError> {
-        let existing_process = self
-            .ongoing_processes
+        Ok(get_process_status(&self.ongoing_processes, &self.stopping_commands, command_id).await)
+    }
+
+    async fn lock_command(&self, command_id: i32) -> OwnedMutexGuard<()> {
+        let mut command_locks = self.command_locks.lock().await;
+
+        // Locks that nobody holds or waits for can go, so deleted commands don't keep theirs
+        command_locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+
+        let lock = Arc::clone(command_locks.entry(command_id).or_default());
+
+        drop(command_locks);
+
+        lock.lock_owned().await
+    }
+
+    async fn running_instance_indices(&self, command_id: i32) -> Vec<i32> {
+        self.ongoing_processes
             .lock()
             .await
             .iter()
             .find(|p| p.command_id == command_id)
-            .is_some();
-
-        if existing_process {
-            Ok(ProcessStatus::Running)
-        } else if self.stopping_commands.lock().await.contains(&command_id) {
-            Ok(ProcessStatus::Stopping)
-        } else {
-            Ok(ProcessStatus::Stopped)
-        }
+            .map(|p| p.instances.iter().map(|i| i.index).collect())
+            .unwrap_or_default()
     }
 
     pub async fn kill_process(&self, command_id: i32) -> Result<(), AppCommandError> {
-        let mut ongoing_processes = self.ongoing_processes.lock().await;
+        let _command_lock = self.lock_command(command_id).await;
 
-        let index = ongoing_processes
-            .iter()
-            .position(|p| p.command_id == command_id);
+        self.stop_process(command_id, Some("Command killed.".into()))
+            .await
+    }
 
-        if let Some(index) = index {
-            let process = ongoing_processes.swap_remove(index);
+    pub async fn run_process(&self, command: command::Data) -> Result<(), AppCommandError> {
+        let _command_lock = self.lock_command(command.id).await;
 
-            // Drop the lock first to avoid deadlock
-            drop(ongoing_processes);
-            self.stopping_commands.lock().await.push(command_id);
-            send_command_update_event(&self.app_handle, command_id)?;
+        self.start_process(command).await
+    }
 
-            // Stop waiting for the process to finish first, otherwise we will deadlock!
-            process.status_join_handle.abort();
+    pub async fn restart_process(&self, command: command::Data) -> Result<(), AppCommandError> {
+        // Keep the lock for the whole restart, so a second request can only start after this one
+        // is done, and will then see the process we spawned here
+        let _command_lock = self.lock_command(command.id).await;
 
-            debug!("Waiting for child lock");
+        let instance_count = self.running_instance_indices(command.id).await.len();
 
-            let mut child = process.child.lock().await;
+        self.stop_process(command.id, None).await?;
 
-            debug!("Got child lock");
+        self.log_writer
+            .write(
+                command.id,
+                CommandLogLineSource::INFO,
+                "Restarting...".into(),
+                vec![],
+            )
+            .await?;
 
-            // We can only send signals in unix
-            #[cfg(target_family = "unix")]
-            {
-                // Send SIGINT to the child process
-                kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM)?;
+        self.start_run(command.id).await?;
 
-                debug!("Sent SIGTERM to child process with pid {}", child.id());
+        for index in 0..instance_count.max(1) as i32 {
+            self.start_instance(&command, index).await?;
+        }
 
-                // Wait for 5 seconds or until the child process exits
-                select! {
-                    _ = sleep(Duration::from_secs(5)) => {
-                        debug!("Timed out when waiting for child process to exit");
-                    }
-                    _ = child.status() => {}
-                }
-            }
+        Ok(())
+    }
 
-            #[cfg(target_family = "windows")]
-            {
-                // Use taskkill to kill the process tree
-                let status = Command::new("taskkill")
-                    .args(&["/pid", &child.id().to_string(), "/t", "/f"])
-                    .creation_flags(CREATE_NO_WINDOW)
-                    .spawn()?
-                    .status()
-                    .await?;
+    // Starts or stops instances of the command until exactly `instance_count` of them are running
+    pub async fn scale_process(
+        &self,
+        command: command::Data,
+        instance_count: u32,
+    ) -> Result<(), AppCommandError> {
+        let _command_lock = self.lock_command(command.id).await;
+
+        if instance_count == 0 {
+            return self
+                .stop_process(command.id, Some("Command killed.".into()))
+                .await;
+        }
 
-                debug!("Taskkill status: {}", status);
+        let running_indices = self.running_instance_indices(command.id).await;
+        let instance_count = instance_count as usize;
 
-                if child.try_status()?.is_none() {
-                    debug!("Child process is still running, waiting a bit");
+        if running_indices.is_empty() {
+            self.start_run(command.id).await?;
+        }
 
-                    sleep(Duration::from_secs(3)).await;
-                }
+        if running_indices.len() < instance_count {
+            // Reuse the lowest free indices, so INSTANCE_INDEX stays in 0..instance_count
+            let new_indices: Vec<i32> = (0..)
+                .filter(|i| !running_indices.contains(i))
+                .take(instance_count - running_indices.len())
+                .collect();
+
+            for index in new_indices {
+                self.start_instance(&command, index).await?;
             }
+        } else if running_indices.len() > instance_count {
+            let instances = {
+                let mut ongoing_processes = self.ongoing_processes.lock().await;
+
+                match ongoing_processes
+                    .iter_mut()
+                    .find(|p| p.command_id == command.id)
+                {
+                    Some(process) => {
+                        process.instances.sort_by_key(|i| i.index);
+                        process.instances.split_off(instance_count)
+                    }
+                    None => vec![],
+                }
+            };
+
+            let stopped_indices: Vec<i32> = instances.iter().map(|i| i.index).collect();
 
-            // Check if the child process is still running
-            if child.try_status()?.is_none() {
-                debug!("Child process is still running, killing it");
+            kill_instances(instances).await?;
 
-                // Send SIGKILL to the child process
-                child.kill()?;
+            for index in stopped_indices {
+                self.log_writer
+                    .write(
+                        command.id,
+                        CommandLogLineSource::INFO,
+                        format!("Instance {} killed.", index),
+                        vec![command_log_line::instance::set(Some(index))],
+                    )
+                    .await?;
             }
 
-            debug!("Child process is stopped");
+            send_command_update_event(&self.app_handle, command.id)?;
+            let _ = self.status_changes.send(command.id);
+        }
 
-            if let Some(join_handle) = process.output_join_handle.lock().await.take() {
-                debug!("Got output join handle, waiting for output...");
+        Ok(())
+    }
 
-                // Only wait for output for 1 sec, if we don't kill it cleanly, the output might get stuck
-                select! {
-                    _ = sleep(Duration::from_secs(1)) => {}
-                    _ = join_handle => {}
-                }
-            }
+    // Must be called while holding the command lock
+    async fn stop_process(
+        &self,
+        command_id: i32,
+        kill_log: Option<String>,
+    ) -> Result<(), AppCommandError> {
+        let mut ongoing_processes = self.ongoing_processes.lock().await;
 
-            debug!("Finished waiting for output");
+        let index = ongoing_processes
+            .iter()
+            .position(|p| p.command_id == command_id);
 
-            let command_killed_log = "Command killed.".to_string();
+        if let Some(index) = index {
+            let process = ongoing_processes.swap_remove(index);
 
-            self.db_client
-                .command_log_line()
-                .create(
-                    command::id::equals(command_id),
-                    CommandLogLineSource::INFO as i32,
-                    command_killed_log,
-                    timestamp()?,
-                    vec![],
-                )
-                .exec()
-                .await?;
+            // Drop the lock first to avoid deadlock
+            drop(ongoing_processes);
+            self.stopping_commands.lock().await.push(command_id);
+            send_command_update_event(&self.app_handle, command_id)?;
+            let _ = self.status_changes.send(command_id);
 
-            debug!("Created kill command log line");
+            kill_instances(process.instances).await?;
+
+            if let Some(kill_log) = kill_log {
+                self.log_writer
+                    .write(command_id, CommandLogLineSource::INFO, kill_log, vec![])
+                    .await?;
+
+                debug!("Created kill command log line");
+            }
 
             self.db_client
                 .command()
                 .update(
                     command::id::equals(command_id),
                     vec![
-                        command::last_run_result_type::set(Some(LastRunResultType::Killed.as_str().into())),
+                        command::last_run_result_type::set(Some(
+                            LastRunResultType::Killed.as_str().into(),
+                        )),
                         command::last_run_code::set(None),
                     ],
                 )
@@ -239,8 +404,8 @@ impl ProcessManager {
 
             debug!("Removed command from stopping commands");
 
-            send_command_log_update_event(&self.app_handle, command_id)?;
             send_command_update_event(&self.app_handle, command_id)?;
+            let _ = self.status_changes.send(command_id);
 
             debug!("Sent update events");
         }
@@ -248,75 +413,167 @@ impl ProcessManager {
         Ok(())
     }
 
-    pub async fn run_process(&self, command: command::Data) -> Result<(), AppCommandError> {
-        #[cfg(target_family = "windows")]
-        let mut cmd = 
-            // Powershell is significantly slower, but you have to use it to run commands on
-            // these kinds of paths
-            if command.cwd.starts_with("\\\\") {
-                let mut cmd = Command::new("powershell");
-                cmd.arg("-Command");
-                cmd.arg(command.command.clone());
-
-                cmd.creation_flags(CREATE_NO_WINDOW);
-
-                cmd
-            } else {
-                let mut cmd = Command::new("cmd");
-                cmd.arg("/s");
-                cmd.arg("/c");
+    // Must be called while holding the command lock
+    async fn start_process(&self, command: command::Data) -> Result<(), AppCommandError> {
+        // Since we hold the command lock, nothing else can add an entry for this command until
+        // we're done, so checking here is enough to prevent spawning it twice
+        if self
+            .ongoing_processes
+            .lock()
+            .await
+            .iter()
+            .any(|p| p.command_id == command.id)
+        {
+            return Err(AppCommandError::ClientError(ClientError::AlreadyRunning));
+        }
 
-                cmd.raw_arg(command.command.clone());
+        self.start_run(command.id).await?;
 
-                cmd.creation_flags(CREATE_NO_WINDOW);
+        self.start_instance(&command, 0).await
+    }
 
-                cmd
-            };
-        
+    // The log lines of the new run get their own sequence, and the error and warning counts start
+    // over
+    async fn start_run(&self, command_id: i32) -> Result<(), AppCommandError> {
+        self.log_writer.start_run(command_id).await?;
+
+        self.run_results
+            .lock()
+            .await
+            .insert(command_id, RunResult::default());
+
+        // Held while storing, so counts of the previous run can't be stored after these
+        let mut level_counts = self.level_counts.lock().await;
+        level_counts.insert(command_id, LevelCounts::default());
+
+        self.db_client
+            .command()
+            .update(
+                command::id::equals(command_id),
+                vec![
+                    command::last_run_error_count::set(0),
+                    command::last_run_warning_count::set(0),
+                ],
+            )
+            .exec()
+            .await?;
+
+        drop(level_counts);
+
+        Ok(())
+    }
+
+    // Returns whether the run wasn't marked before
+    async fn mark_run_failed(&self, command_id: i32) -> bool {
+        let mut run_results = self.run_results.lock().await;
+        let run_result = run_results.entry(command_id).or_default();
+
+        let newly_failed = !run_result.failed;
+        run_result.failed = true;
+
+        newly_failed
+    }
+
+    // The counts are stored and sent with the next status events
+    async fn count_level(&self, command_id: i32, level: LogLevel) {
+        {
+            let mut level_counts = self.level_counts.lock().await;
+            let counts = level_counts.entry(command_id).or_default();
+
+            match level {
+                LogLevel::Warn => counts.warnings += 1,
+                LogLevel::Error | LogLevel::Fatal => counts.errors += 1,
+                _ => return,
+            }
+
+            counts.changed = true;
+        }
 
-        #[cfg(target_family = "unix")]
-        let mut cmd = {
-            let mut cmd = Command::new("bash");
-            cmd.arg("-c").arg(&command.command);
-            cmd
+        let _ = self.status_changes.send(command_id);
+    }
+
+    // Must be called while holding the command lock
+    async fn start_instance(
+        &self,
+        command: &command::Data,
+        instance_index: i32,
+    ) -> Result<(), AppCommandError> {
+        let command_id = command.id;
+
+        let output_handler = Arc::new(OutputHandler {
+            command_id,
+            instance_index,
+            log_writer: self.log_writer.clone(),
+            manager: self.this.clone(),
+            notifier: CommandNotifier::load(&self.db_client, (*self.app_handle).clone(), command)
+                .await?,
+            triggers: CommandTriggers::load(&self.db_client, command_id).await?,
+            log_parser_mode: LogParserMode::from_command(command),
+            level_detector: LevelDetector::from_command(command),
+            multiline_rule: MultilineRule::from_command(command),
+        });
+
+        // Both streams write to the same pipe, so their lines are read in the order they were
+        // written. Which stream a line came from is lost, all of them are stored as stdout.
+        let (merged_reader, merged_writer) = if command.merge_stderr {
+            let (reader, writer) = pipe()?;
+            (Some(reader), Some(writer))
+        } else {
+            (None, None)
         };
 
-        cmd.current_dir(command.cwd.clone())
-            .stdin(Stdio::null())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+        let shell_environment = self.shell_environment.read().await;
+
+        let child = build_command(command, shell_environment.as_ref()).and_then(|mut cmd| {
+            cmd.current_dir(command.cwd.clone())
+                .env("INSTANCE_INDEX", instance_index.to_string())
+                .stdin(Stdio::null());
 
-        let child = cmd.spawn();
+            match merged_writer {
+                Some(writer) => {
+                    let stdout_writer = writer.try_clone().map_err(|e| e.to_string())?;
+                    cmd.stdout(Stdio::from(stdout_writer))
+                        .stderr(Stdio::from(writer));
+                }
+                None => {
+                    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+                }
+            }
+
+            // The writers are closed when cmd is dropped, so the reader ends with the process
+            cmd.spawn().map_err(|e| e.to_string())
+        });
+
+        drop(shell_environment);
 
         if let Err(spawn_error) = child {
             let error_message = format!("Command failed to start: {}", spawn_error);
 
-            self.db_client
-                .command_log_line()
-                .create(
-                    command::id::equals(command.id),
-                    CommandLogLineSource::INFO as i32,
+            self.log_writer
+                .write(
+                    command_id,
+                    CommandLogLineSource::INFO,
                     error_message,
-                    timestamp()?,
-                    vec![],
+                    vec![command_log_line::instance::set(Some(instance_index))],
                 )
-                .exec()
                 .await?;
 
             self.db_client
                 .command()
                 .update(
-                    command::id::equals(command.id),
+                    command::id::equals(command_id),
                     vec![
-                        command::last_run_result_type::set(Some(LastRunResultType::Error.as_str().into())),
+                        command::last_run_result_type::set(Some(
+                            LastRunResultType::Error.as_str().into(),
+                        )),
                         command::last_run_code::set(None),
                     ],
                 )
                 .exec()
                 .await?;
 
-            send_command_log_update_event(&self.app_handle, command.id)?;
-            send_command_update_event(&self.app_handle, command.id)?;
+            send_command_update_event(&self.app_handle, command_id)?;
+            let _ = self.status_changes.send(command_id);
 
             return Ok(());
         };
@@ -324,26 +581,21 @@ impl ProcessManager {
         let mut child = child.expect("Spawn errors to already be handled");
 
         let out_process = {
-            let db = Arc::clone(&self.db_client);
-            let app_handle = Arc::clone(&self.app_handle);
-            let stdout = child.stdout.take().unwrap();
+            let output_handler = Arc::clone(&output_handler);
+            let stdout = child.stdout.take();
             async move {
-                let mut lines = BufReader::new(stdout).lines();
-
-                while let Some(line) = lines.try_next().await? {
-                    trace!("{} stdout: {}", command.id, line);
-                    db.command_log_line()
-                        .create(
-                            command::id::equals(command.id),
-                            CommandLogLineSource::STDOUT as i32,
-                            line,
-                            timestamp()?,
-                            vec![],
-                        )
-                        .exec()
-                        .await?;
-                    trace!("Written to db");
-                    send_command_log_update_event(&app_handle, command.id)?;
+                match (stdout, merged_reader) {
+                    (Some(stdout), _) => {
+                        output_handler
+                            .read_output(CommandLogLineSource::STDOUT, stdout)
+                            .await?
+                    }
+                    (None, Some(reader)) => {
+                        output_handler
+                            .read_output(CommandLogLineSource::STDOUT, Unblock::new(reader))
+                            .await?
+                    }
+                    (None, None) => {}
                 }
 
                 debug!("Stdout finished");
@@ -353,26 +605,13 @@ impl ProcessManager {
         };
 
         let err_process = {
-            let db = Arc::clone(&self.db_client);
-            let app_handle = Arc::clone(&self.app_handle);
-            let stderr = child.stderr.take().unwrap();
+            let output_handler = Arc::clone(&output_handler);
+            let stderr = child.stderr.take();
             async move {
-                let mut lines = BufReader::new(stderr).lines();
-
-                while let Some(line) = lines.try_next().await? {
-                    trace!("{} stderr: {}", command.id, line);
-                    db.command_log_line()
-                        .create(
-                            command::id::equals(command.id),
-                            CommandLogLineSource::STDERR as i32,
-                            line,
-                            timestamp()?,
-                            vec![],
-                        )
-                        .exec()
+                if let Some(stderr) = stderr {
+                    output_handler
+                        .read_output(CommandLogLineSource::STDERR, stderr)
                         .await?;
-                    trace!("Written to db");
-                    send_command_log_update_event(&app_handle, command.id)?;
                 }
 
                 debug!("Stderr finished");
@@ -391,9 +630,17 @@ impl ProcessManager {
 
         let child_mutex = Arc::new(Mutex::new(child));
 
+        // Hold the lock until the instance is registered, otherwise a process that exits right
+        // away would try to remove itself before it's added
+        let mut ongoing_processes = self.ongoing_processes.lock().await;
+
         let status_join_handle = {
             let db = Arc::clone(&self.db_client);
+            let log_writer = self.log_writer.clone();
+            let status_changes = self.status_changes.clone();
             let ongoing_processes = Arc::clone(&self.ongoing_processes);
+            let stopping_commands = Arc::clone(&self.stopping_commands);
+            let run_results = Arc::clone(&self.run_results);
             let output_mutex = Arc::clone(&output_join_mutex);
             let spawned_child_mutex = Arc::clone(&child_mutex);
             let app_handle = Arc::clone(&self.app_handle);
@@ -410,42 +657,82 @@ impl ProcessManager {
                     output.await??;
                 }
 
-                ongoing_processes
+                // Recorded before the instance is removed, so it's in before the last one reads it
+                run_results
                     .lock()
                     .await
-                    .retain(|p| p.command_id != command.id);
+                    .entry(command_id)
+                    .or_default()
+                    .record_exit(status);
+
+                let is_last_instance = {
+                    let mut ongoing_processes = ongoing_processes.lock().await;
+
+                    for process in ongoing_processes.iter_mut() {
+                        if process.command_id == command_id {
+                            process.instances.retain(|i| i.index != instance_index);
+                        }
+                    }
+
+                    ongoing_processes.retain(|p| !p.instances.is_empty());
+
+                    !ongoing_processes.iter().any(|p| p.command_id == command_id)
+                };
 
                 debug!("Removed process from ongoing processes");
 
+                // Stopping a command on purpose isn't worth a notification
+                if !stopping_commands.lock().await.contains(&command_id) {
+                    output_handler.notifier.on_exit(&status);
+                }
+
                 let command_exit_log = format!("Command finished with {}", status);
-                db.command_log_line()
-                    .create(
-                        command::id::equals(command.id),
-                        CommandLogLineSource::INFO as i32,
+                log_writer
+                    .write(
+                        command_id,
+                        CommandLogLineSource::INFO,
                         command_exit_log,
-                        timestamp()?,
-                        vec![],
+                        vec![command_log_line::instance::set(Some(instance_index))],
                     )
-                    .exec()
                     .await?;
 
                 debug!("Created exit command log line");
 
-                db.command()
-                    .update(
-                        command::id::equals(command.id),
-                        vec![
-                            command::last_run_result_type::set(Some(LastRunResultType::Exit.as_str().into())),
-                            command::last_run_code::set(status.code().map(|c| c.to_string())),
-                        ],
-                    )
-                    .exec()
-                    .await?;
+                // The run only ends when its last instance does
+                if is_last_instance {
+                    let (failed, worst_exit) = match run_results.lock().await.get(&command_id) {
+                        Some(run_result) => {
+                            (run_result.failed, run_result.worst_exit.unwrap_or(status))
+                        }
+                        None => (false, status),
+                    };
+
+                    let result_type = if failed {
+                        LastRunResultType::Failed
+                    } else {
+                        LastRunResultType::Exit
+                    };
+
+                    db.command()
+                        .update(
+                            command::id::equals(command_id),
+                            vec![
+                                command::last_run_result_type::set(Some(
+                                    result_type.as_str().into(),
+                                )),
+                                command::last_run_code::set(
+                                    worst_exit.code().map(|c| c.to_string()),
+                                ),
+                            ],
+                        )
+                        .exec()
+                        .await?;
 
-                debug!("Updated last run result");
+                    debug!("Updated last run result");
+                }
 
-                send_command_log_update_event(&app_handle, command.id)?;
-                send_command_update_event(&app_handle, command.id)?;
+                send_command_update_event(&app_handle, command_id)?;
+                let _ = status_changes.send(command_id);
 
                 debug!("Sent command log update event");
 
@@ -453,35 +740,380 @@ impl ProcessManager {
             }))
         };
 
-        self.ongoing_processes.lock().await.push(OngoingProcess {
-            command_id: command.id,
+        let instance = ProcessInstance {
+            index: instance_index,
             child: child_mutex,
             output_join_handle: output_join_mutex,
             status_join_handle,
-        });
+        };
+
+        match ongoing_processes
+            .iter_mut()
+            .find(|p| p.command_id == command_id)
+        {
+            Some(process) => process.instances.push(instance),
+            None => ongoing_processes.push(OngoingProcess {
+                command_id,
+                instances: vec![instance],
+            }),
+        }
+
+        drop(ongoing_processes);
 
         let start_command_log =
             format!("Running command `{}` at `{}`", command.command, command.cwd);
 
-        self.db_client
-            .command_log_line()
-            .create(
-                command::id::equals(command.id),
-                CommandLogLineSource::INFO as i32,
+        self.log_writer
+            .write(
+                command_id,
+                CommandLogLineSource::INFO,
                 start_command_log,
-                timestamp()?,
-                vec![],
+                vec![command_log_line::instance::set(Some(instance_index))],
             )
-            .exec()
             .await?;
 
-        send_command_log_update_event(&self.app_handle, command.id)?;
-        send_command_update_event(&self.app_handle, command.id)?;
+        send_command_update_event(&self.app_handle, command_id)?;
+        let _ = self.status_changes.send(command_id);
+
+        Ok(())
+    }
+}
+
+// Handles the output lines of one instance, for both stdout and stderr
+struct OutputHandler<R: Runtime> {
+    command_id: i32,
+    instance_index: i32,
+    log_writer: LogWriter,
+    manager: Weak<ProcessManager<R>>,
+    notifier: CommandNotifier<R>,
+    triggers: CommandTriggers,
+    log_parser_mode: LogParserMode,
+    level_detector: LevelDetector,
+    multiline_rule: Option<MultilineRule>,
+}
+
+impl<R: Runtime> OutputHandler<R> {
+    async fn read_output<T: AsyncRead + Unpin>(
+        &self,
+        source: CommandLogLineSource,
+        output: T,
+    ) -> Result<(), AppCommandError> {
+        let mut lines = BufReader::new(output).lines();
+        let mut joiner = LineJoiner::new(self.multiline_rule.clone());
+
+        loop {
+            // A pending entry may still get continuation lines, but shouldn't be held back for
+            // long when the output goes quiet
+            let next_line = if joiner.has_pending() {
+                match timeout(MULTILINE_FLUSH_DELAY, lines.try_next()).await {
+                    Ok(next_line) => Some(next_line?),
+                    Err(_) => None,
+                }
+            } else {
+                Some(lines.try_next().await?)
+            };
+
+            let entry = match next_line {
+                Some(Some(line)) => {
+                    let arrived_at = Instant::now();
+                    trace!(
+                        "{}#{} {}: {}",
+                        self.command_id,
+                        self.instance_index,
+                        source.as_str(),
+                        line
+                    );
+                    joiner.push(line, arrived_at)
+                }
+                Some(None) => {
+                    if let Some(entry) = joiner.finish() {
+                        self.handle_entry(source, entry).await?;
+                    }
+                    return Ok(());
+                }
+                None => joiner.finish(),
+            };
+
+            if let Some(entry) = entry {
+                self.handle_entry(source, entry).await?;
+            }
+        }
+    }
+
+    async fn handle_entry(
+        &self,
+        source: CommandLogLineSource,
+        entry: LogEntry,
+    ) -> Result<(), AppCommandError> {
+        let LogEntry {
+            line,
+            line_count,
+            arrived_at,
+        } = entry;
+
+        self.notifier.on_line(&line);
+        let actions: Vec<_> = self.triggers.matching(source as i32, &line).collect();
+
+        let mut parsed = parse_line(self.log_parser_mode, &line);
+        if parsed.level.is_none() {
+            parsed.level = self
+                .level_detector
+                .detect(&line)
+                .map(|level| level.as_str().into());
+        }
+        let level = parsed.level.as_deref().and_then(LogLevel::parse);
+
+        let mut params = vec![
+            command_log_line::instance::set(Some(self.instance_index)),
+            command_log_line::line_count::set(line_count),
+        ];
+        params.extend(parsed.into_params());
+
+        self.log_writer
+            .write_arrived(self.command_id, arrived_at, source, line, params)
+            .await?;
+        trace!("Written to db");
+
+        if let (Some(level), Some(manager)) = (level, self.manager.upgrade()) {
+            manager.count_level(self.command_id, level).await;
+        }
+
+        for action in actions {
+            self.run_trigger_action(action).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn run_trigger_action(&self, action: TriggerAction) -> Result<(), AppCommandError> {
+        let Some(manager) = self.manager.upgrade() else {
+            return Ok(());
+        };
+        let command_id = self.command_id;
+
+        match action {
+            TriggerAction::MarkFailed => {
+                if manager.mark_run_failed(command_id).await {
+                    self.log_writer
+                        .write(
+                            command_id,
+                            CommandLogLineSource::INFO,
+                            "Run marked as failed by a trigger.".into(),
+                            vec![command_log_line::instance::set(Some(self.instance_index))],
+                        )
+                        .await?;
+                }
+            }
+            // Stopping waits for the output readers to finish, so these can't be awaited here
+            TriggerAction::StopCommand => {
+                spawn(wrap_with_error_printer("trigger action", async move {
+                    manager.kill_process(command_id).await
+                }));
+            }
+            TriggerAction::StartCommand(target_command_id) => {
+                spawn(wrap_with_error_printer("trigger action", async move {
+                    let target_command = manager
+                        .db_client
+                        .command()
+                        .find_unique(command::id::equals(target_command_id))
+                        .exec()
+                        .await?
+                        .ok_or(AppCommandError::ClientError(ClientError::CommandNotFound))?;
+
+                    match manager.run_process(target_command).await {
+                        // The trigger can match again while the target is still running
+                        Err(AppCommandError::ClientError(ClientError::AlreadyRunning)) => Ok(()),
+                        result => result,
+                    }
+                }));
+            }
+        }
 
         Ok(())
     }
 }
 
+async fn get_process_status(
+    ongoing_processes: &Mutex<Vec<OngoingProcess>>,
+    stopping_commands: &Mutex<Vec<i32>>,
+    command_id: i32,
+) -> ProcessStatus {
+    let instance_count = ongoing_processes
+        .lock()
+        .await
+        .iter()
+        .find(|p| p.command_id == command_id)
+        .map(|p| p.instances.len());
+
+    if let Some(instance_count) = instance_count {
+        ProcessStatus::Running {
+            instances: instance_count as u32,
+        }
+    } else if stopping_commands.lock().await.contains(&command_id) {
+        ProcessStatus::Stopping
+    } else {
+        ProcessStatus::Stopped
+    }
+}
+
+// Stores the counts that changed since they were last stored
+async fn store_level_counts<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    db_client: &PrismaClient,
+    level_counts: &Mutex<HashMap<i32, LevelCounts>>,
+    command_id: i32,
+) -> Result<(), AppCommandError> {
+    let mut level_counts = level_counts.lock().await;
+
+    let Some(counts) = level_counts.get_mut(&command_id).filter(|c| c.changed) else {
+        return Ok(());
+    };
+
+    db_client
+        .command()
+        .update(
+            command::id::equals(command_id),
+            vec![
+                command::last_run_error_count::set(counts.errors),
+                command::last_run_warning_count::set(counts.warnings),
+            ],
+        )
+        .exec()
+        .await?;
+
+    counts.changed = false;
+
+    drop(level_counts);
+
+    send_command_update_event(app_handle, command_id)?;
+
+    Ok(())
+}
+
+async fn send_status_events<R: Runtime>(
+    app_handle: Arc<AppHandle<R>>,
+    db_client: Arc<PrismaClient>,
+    ongoing_processes: Arc<Mutex<Vec<OngoingProcess>>>,
+    stopping_commands: Arc<Mutex<Vec<i32>>>,
+    level_counts: Arc<Mutex<HashMap<i32, LevelCounts>>>,
+    mut receiver: UnboundedReceiver<i32>,
+) {
+    while let Some(command_id) = receiver.recv().await {
+        sleep(STATUS_EVENT_DEBOUNCE).await;
+
+        let mut command_ids = vec![command_id];
+
+        while let Ok(command_id) = receiver.try_recv() {
+            if !command_ids.contains(&command_id) {
+                command_ids.push(command_id);
+            }
+        }
+
+        for command_id in command_ids {
+            if let Err(err) =
+                store_level_counts(&app_handle, &db_client, &level_counts, command_id).await
+            {
+                error!(
+                    "Failed to store log level counts: {}",
+                    serde_json::to_string(&err).unwrap_or_default()
+                );
+            }
+
+            let status =
+                get_process_status(&ongoing_processes, &stopping_commands, command_id).await;
+
+            if let Err(err) = send_process_status_event(&app_handle, command_id, status) {
+                error!("Failed to send process status event: {}", err);
+            }
+        }
+    }
+}
+
+// Stops all the given instances in parallel, so a large pool doesn't take 5 seconds per instance
+async fn kill_instances(instances: Vec<ProcessInstance>) -> Result<(), AppCommandError> {
+    let handles: Vec<_> = instances
+        .into_iter()
+        .map(|instance| spawn(kill_instance(instance)))
+        .collect();
+
+    for handle in handles {
+        handle.await??;
+    }
+
+    Ok(())
+}
+
+async fn kill_instance(instance: ProcessInstance) -> Result<(), AppCommandError> {
+    // Stop waiting for the process to finish first, otherwise we will deadlock!
+    instance.status_join_handle.abort();
+
+    debug!("Waiting for child lock");
+
+    let mut child = instance.child.lock().await;
+
+    debug!("Got child lock");
+
+    // We can only send signals in unix
+    #[cfg(target_family = "unix")]
+    {
+        // Send SIGINT to the child process
+        kill(Pid::from_raw(child.id() as i32), Signal::SIGTERM)?;
+
+        debug!("Sent SIGTERM to child process with pid {}", child.id());
+
+        // Wait for 5 seconds or until the child process exits
+        select! {
+            _ = sleep(Duration::from_secs(5)) => {
+                debug!("Timed out when waiting for child process to exit");
+            }
+            _ = child.status() => {}
+        }
+    }
+
+    #[cfg(target_family = "windows")]
+    {
+        // Use taskkill to kill the process tree
+        let status = Command::new("taskkill")
+            .args(&["/pid", &child.id().to_string(), "/t", "/f"])
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()?
+            .status()
+            .await?;
+
+        debug!("Taskkill status: {}", status);
+
+        if child.try_status()?.is_none() {
+            debug!("Child process is still running, waiting a bit");
+
+            sleep(Duration::from_secs(3)).await;
+        }
+    }
+
+    // Check if the child process is still running
+    if child.try_status()?.is_none() {
+        debug!("Child process is still running, killing it");
+
+        // Send SIGKILL to the child process
+        child.kill()?;
+    }
+
+    debug!("Child process is stopped");
+
+    if let Some(join_handle) = instance.output_join_handle.lock().await.take() {
+        debug!("Got output join handle, waiting for output...");
+
+        // Only wait for output for 1 sec, if we don't kill it cleanly, the output might get stuck
+        select! {
+            _ = sleep(Duration::from_secs(1)) => {}
+            _ = join_handle => {}
+        }
+    }
+
+    debug!("Finished waiting for output");
+
+    Ok(())
+}
+
 async fn wrap_with_error_printer<R, T: Future<Output = Result<R, AppCommandError>>>(
     name: &str,
     future: T,
@@ -500,3 +1132,419 @@ async fn wrap_with_error_printer<R, T: Future<Output = Result<R, AppCommandError
 
     res
 }
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use std::env::temp_dir;
+
+    use prisma_client_rust::Direction;
+    use tauri::test::{mock_app, MockRuntime};
+    use tokio::{join, sync::RwLock};
+
+    use super::*;
+    use crate::{test_utils::TestDatabase, triggers::TriggerActionKind};
+
+    async fn create_test_manager(
+        command_line: &str,
+    ) -> (
+        TestDatabase,
+        Arc<ProcessManager<MockRuntime>>,
+        command::Data,
+    ) {
+        let db = TestDatabase::new().await;
+
+        let command = db
+            .command()
+            .create(
+                "test".into(),
+                temp_dir().to_string_lossy().into(),
+                command_line.into(),
+                "n".into(),
+                vec![],
+            )
+            .exec()
+            .await
+            .unwrap();
+
+        let app = mock_app();
+        let manager = ProcessManager::new(
+            Arc::new(app.handle()),
+            Arc::clone(&db.client),
+            LogWriter::new(Arc::clone(&db.client)),
+            Arc::new(RwLock::new(None)),
+        );
+
+        (db, manager, command)
+    }
+
+    async fn running_instance_count(
+        manager: &ProcessManager<MockRuntime>,
+        command_id: i32,
+    ) -> usize {
+        manager
+            .ongoing_processes
+            .lock()
+            .await
+            .iter()
+            .find(|p| p.command_id == command_id)
+            .map(|p| p.instances.len())
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn concurrent_runs_only_spawn_once() {
+        let (_db, manager, command) = create_test_manager("sleep 10").await;
+
+        let (first, second) = join!(
+            manager.run_process(command.clone()),
+            manager.run_process(command.clone())
+        );
+
+        let results = [first, second];
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(results.iter().any(|r| matches!(
+            r,
+            Err(AppCommandError::ClientError(ClientError::AlreadyRunning))
+        )));
+        assert_eq!(running_instance_count(&manager, command.id).await, 1);
+
+        manager.kill_process(command.id).await.unwrap();
+
+        assert_eq!(running_instance_count(&manager, command.id).await, 0);
+    }
+
+    #[tokio::test]
+    async fn run_after_kill_spawns_again() {
+        let (_db, manager, command) = create_test_manager("sleep 10").await;
+
+        manager.run_process(command.clone()).await.unwrap();
+        manager.kill_process(command.id).await.unwrap();
+        manager.run_process(command.clone()).await.unwrap();
+
+        assert_eq!(running_instance_count(&manager, command.id).await, 1);
+
+        manager.kill_process(command.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_restarts_keep_one_process() {
+        let (_db, manager, command) = create_test_manager("sleep 10").await;
+
+        manager.run_process(command.clone()).await.unwrap();
+
+        let (first, second) = join!(
+            manager.restart_process(command.clone()),
+            manager.restart_process(command.clone())
+        );
+
+        first.unwrap();
+        second.unwrap();
+
+        assert_eq!(running_instance_count(&manager, command.id).await, 1);
+
+        manager.kill_process(command.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn scale_process_up_and_down() {
+        let (_db, manager, command) = create_test_manager("sleep 10").await;
+
+        manager.scale_process(command.clone(), 3).await.unwrap();
+
+        assert_eq!(running_instance_count(&manager, command.id).await, 3);
+        assert_eq!(
+            manager.running_instance_indices(command.id).await,
+            vec![0, 1, 2]
+        );
+
+        manager.scale_process(command.clone(), 1).await.unwrap();
+
+        assert_eq!(manager.running_instance_indices(command.id).await, vec![0]);
+
+        manager.scale_process(command.clone(), 2).await.unwrap();
+
+        assert_eq!(running_instance_count(&manager, command.id).await, 2);
+        assert!(matches!(
+            manager.check_process_status(command.id).await.unwrap(),
+            ProcessStatus::Running { instances: 2 }
+        ));
+
+        manager.scale_process(command.clone(), 0).await.unwrap();
+
+        assert!(matches!(
+            manager.check_process_status(command.id).await.unwrap(),
+            ProcessStatus::Stopped
+        ));
+    }
+
+    #[tokio::test]
+    async fn unused_command_locks_are_removed() {
+        let (_db, manager, _) = create_test_manager("true").await;
+
+        drop(manager.lock_command(1).await);
+        let _guard = manager.lock_command(2).await;
+
+        let command_locks = manager.command_locks.lock().await;
+        assert_eq!(command_locks.keys().collect::<Vec<_>>(), vec![&2]);
+    }
+
+    async fn wait_until_stopped(manager: &ProcessManager<MockRuntime>, command_id: i32) {
+        for _ in 0..200 {
+            if running_instance_count(manager, command_id).await == 0 {
+                return;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("Command didn't stop");
+    }
+
+    async fn stored_lines(
+        manager: &ProcessManager<MockRuntime>,
+        command_id: i32,
+    ) -> Vec<command_log_line::Data> {
+        manager
+            .db_client
+            .command_log_line()
+            .find_many(vec![command_log_line::command_id::equals(command_id)])
+            .order_by(command_log_line::run::order(Direction::Asc))
+            .order_by(command_log_line::sequence::order(Direction::Asc))
+            .exec()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn high_rate_output_keeps_arrival_order() {
+        let (_db, manager, command) = create_test_manager(
+            "for i in $(seq 1 2000); do echo \"out $i\"; echo \"err $i\" >&2; done",
+        )
+        .await;
+
+        manager.run_process(command.clone()).await.unwrap();
+        wait_until_stopped(&manager, command.id).await;
+
+        let log_lines = stored_lines(&manager, command.id).await;
+
+        // Every line of the run has its own place, and the time never goes backwards
+        assert!(log_lines.iter().all(|l| l.run == 1));
+        assert!(log_lines.windows(2).all(|w| w[0].sequence < w[1].sequence));
+        assert!(log_lines
+            .windows(2)
+            .all(|w| w[0].timestamp <= w[1].timestamp));
+
+        for (source, prefix) in [
+            (CommandLogLineSource::STDOUT, "out"),
+            (CommandLogLineSource::STDERR, "err"),
+        ] {
+            let lines: Vec<_> = log_lines
+                .iter()
+                .filter(|l| l.source == source as i32)
+                .map(|l| l.line.clone())
+                .collect();
+            let expected: Vec<_> = (1..=2000).map(|i| format!("{} {}", prefix, i)).collect();
+
+            assert_eq!(lines, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn merged_stderr_keeps_exact_order() {
+        let (_db, manager, command) = create_test_manager(
+            "for i in $(seq 1 500); do echo \"out $i\"; echo \"err $i\" >&2; done",
+        )
+        .await;
+
+        let command = manager
+            .db_client
+            .command()
+            .update(
+                command::id::equals(command.id),
+                vec![command::merge_stderr::set(true)],
+            )
+            .exec()
+            .await
+            .unwrap();
+
+        manager.run_process(command.clone()).await.unwrap();
+        wait_until_stopped(&manager, command.id).await;
+
+        let lines: Vec<_> = stored_lines(&manager, command.id)
+            .await
+            .into_iter()
+            .filter(|l| l.source == CommandLogLineSource::STDOUT as i32)
+            .map(|l| l.line)
+            .collect();
+        let expected: Vec<_> = (1..=500)
+            .flat_map(|i| [format!("out {}", i), format!("err {}", i)])
+            .collect();
+
+        assert_eq!(lines, expected);
+    }
+
+    #[tokio::test]
+    async fn each_run_gets_its_own_sequence() {
+        let (_db, manager, command) = create_test_manager("seq 1 500").await;
+
+        for _ in 0..2 {
+            manager.run_process(command.clone()).await.unwrap();
+            wait_until_stopped(&manager, command.id).await;
+        }
+
+        let log_lines = stored_lines(&manager, command.id).await;
+
+        for run in [1, 2] {
+            let lines: Vec<_> = log_lines
+                .iter()
+                .filter(|l| l.run == run && l.source == CommandLogLineSource::STDOUT as i32)
+                .collect();
+
+            assert_eq!(lines.len(), 500);
+            assert_eq!(lines[0].line, "1");
+            assert!(lines.windows(2).all(|w| w[0].sequence < w[1].sequence));
+        }
+    }
+
+    // Like the app pages through newer lines
+    async fn newer_lines(
+        manager: &ProcessManager<MockRuntime>,
+        command_id: i32,
+        last_id: Option<i32>,
+    ) -> Vec<command_log_line::Data> {
+        let mut query = manager
+            .db_client
+            .command_log_line()
+            .find_many(vec![command_log_line::command_id::equals(command_id)])
+            .order_by(command_log_line::run::order(Direction::Asc))
+            .order_by(command_log_line::sequence::order(Direction::Asc))
+            .order_by(command_log_line::id::order(Direction::Asc));
+
+        if let Some(last_id) = last_id {
+            query = query.cursor(command_log_line::id::equals(last_id)).skip(1);
+        }
+
+        query.take(50).exec().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn cursor_paging_returns_every_line() {
+        let (_db, manager, command) = create_test_manager(
+            "for i in $(seq 1 300); do echo \"out $i\"; echo \"  detail $i\"; echo \"err $i\" >&2; done",
+        )
+        .await;
+
+        // Multi-line entries are held back while the other stream goes on
+        let command = manager
+            .db_client
+            .command()
+            .update(
+                command::id::equals(command.id),
+                vec![command::multiline_mode::set(Some("indentation".into()))],
+            )
+            .exec()
+            .await
+            .unwrap();
+
+        manager.run_process(command.clone()).await.unwrap();
+
+        let mut seen = vec![];
+        let mut last_id = None;
+        let mut stopped = false;
+
+        // Pages while the lines are written, then until nothing new comes
+        loop {
+            let page = newer_lines(&manager, command.id, last_id).await;
+
+            if let Some(log_line) = page.last() {
+                last_id = Some(log_line.id);
+            }
+            seen.extend(page.iter().map(|l| l.id));
+
+            if page.is_empty() {
+                if stopped {
+                    break;
+                }
+                stopped = running_instance_count(&manager, command.id).await == 0;
+                sleep(Duration::from_millis(200)).await;
+            }
+        }
+
+        let stored: Vec<_> = stored_lines(&manager, command.id)
+            .await
+            .iter()
+            .map(|l| l.id)
+            .collect();
+
+        // Every "out" line carries its detail, and the run ends with the exit line
+        assert_eq!(stored.len(), 300 * 2 + 1);
+        assert!(stored.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(seen, stored);
+    }
+
+    async fn wait_for_run_result(
+        manager: &ProcessManager<MockRuntime>,
+        command_id: i32,
+    ) -> command::Data {
+        for _ in 0..200 {
+            let command = manager
+                .db_client
+                .command()
+                .find_unique(command::id::equals(command_id))
+                .exec()
+                .await
+                .unwrap()
+                .unwrap();
+
+            if command.last_run_result_type.is_some() {
+                return command;
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+
+        panic!("Run result wasn't stored");
+    }
+
+    #[tokio::test]
+    async fn run_result_keeps_worst_exit_of_all_instances() {
+        let (_db, manager, command) =
+            create_test_manager("if [ \"$INSTANCE_INDEX\" = 0 ]; then exit 3; fi; sleep 0.5").await;
+
+        manager.scale_process(command.clone(), 2).await.unwrap();
+
+        let command = wait_for_run_result(&manager, command.id).await;
+
+        // The instance that succeeded exits last, but the run still failed
+        assert_eq!(command.last_run_result_type.as_deref(), Some("exit"));
+        assert_eq!(command.last_run_code.as_deref(), Some("3"));
+    }
+
+    #[tokio::test]
+    async fn run_marked_failed_by_any_instance() {
+        let (_db, manager, command) = create_test_manager(
+            "if [ \"$INSTANCE_INDEX\" = 0 ]; then echo boom; exit 0; fi; sleep 0.5",
+        )
+        .await;
+
+        manager
+            .db_client
+            .command_trigger()
+            .create_unchecked(
+                command.id,
+                "boom".into(),
+                TriggerActionKind::MarkFailed.as_str().into(),
+                vec![],
+            )
+            .exec()
+            .await
+            .unwrap();
+
+        manager.scale_process(command.clone(), 2).await.unwrap();
+
+        let command = wait_for_run_result(&manager, command.id).await;
+
+        // Only the first instance printed the line, the other one exits last
+        assert_eq!(command.last_run_result_type.as_deref(), Some("failed"));
+        assert_eq!(command.last_run_code.as_deref(), Some("0"));
+    }
+}
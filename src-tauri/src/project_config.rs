@@ -0,0 +1,541 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use log::{debug, error, warn};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use prisma_client_rust::Direction;
+use tauri::{AppHandle, Runtime};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    time::{sleep, Duration},
+};
+
+use crate::{
+    command_config::{absolute_cwd, deserialize_config, env_to_json, CommandConfigFormat},
+    errors::{AppCommandError, ClientError},
+    events::send_command_update_event,
+    prisma::{_prisma::PrismaClient, command},
+    utils::get_midpoint_string,
+};
+
+pub const PROJECT_CONFIG_FILE_NAME: &str = "launchpane.toml";
+
+// Editors often write a file in several steps, wait for them to finish before syncing
+const SYNC_DEBOUNCE: Duration = Duration::from_millis(300);
+
+// The same file can be reached through symlinks or `..`, origins are stored by the canonical path
+// so it's only linked once. A deleted file can't be resolved, but its directory might still be.
+fn canonical_config_path(config_path: &Path) -> PathBuf {
+    if let Ok(path) = config_path.canonicalize() {
+        return path;
+    }
+
+    match (config_path.parent(), config_path.file_name()) {
+        (Some(dir), Some(name)) => dir
+            .canonicalize()
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|_| config_path.to_path_buf()),
+        _ => config_path.to_path_buf(),
+    }
+}
+
+// Reads the commands in the given launchpane.toml, and makes the linked Command rows match them.
+// Returns the rows that were created or changed.
+pub async fn sync_project_config<R: Runtime>(
+    client: &PrismaClient,
+    app: &AppHandle<R>,
+    config_path: &Path,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    let config_path = canonical_config_path(config_path);
+
+    // A deleted file orphans all of its commands
+    let definitions = match fs::read_to_string(&config_path) {
+        Ok(content) => deserialize_config(&content, CommandConfigFormat::Toml)?.commands,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => vec![],
+        Err(err) => return Err(err.into()),
+    };
+
+    let origin = config_path.to_string_lossy().to_string();
+    let project_root = config_path.parent().map(Path::to_path_buf);
+
+    let results = client
+        ._transaction()
+        .run(|client| async move {
+            let linked_commands = client
+                .command()
+                .find_many(vec![command::origin::equals(Some(origin.clone()))])
+                .exec()
+                .await?;
+
+            let mut last_order = client
+                .command()
+                .find_first(vec![])
+                .order_by(command::order::order(Direction::Desc))
+                .exec()
+                .await?
+                .map(|c| c.order)
+                .unwrap_or_default();
+
+            let mut seen_names = HashSet::new();
+            let mut results = vec![];
+
+            for definition in definitions {
+                if !seen_names.insert(definition.name.clone()) {
+                    warn!(
+                        "Command `{}` is defined more than once in {}",
+                        definition.name, origin
+                    );
+                    continue;
+                }
+
+                let cwd = absolute_cwd(&definition.cwd, project_root.as_deref())?;
+                let env = env_to_json(&definition.env)?;
+
+                let linked = linked_commands
+                    .iter()
+                    .find(|c| c.origin_name.as_deref() == Some(definition.name.as_str()));
+
+                match linked {
+                    Some(linked) => {
+                        if linked.command == definition.command
+                            && linked.cwd == cwd
+                            && linked.env == env
+                            && !linked.orphaned
+                        {
+                            continue;
+                        }
+
+                        let result = client
+                            .command()
+                            .update(
+                                command::id::equals(linked.id),
+                                vec![
+                                    command::command::set(definition.command),
+                                    command::cwd::set(cwd),
+                                    command::env::set(env),
+                                    command::orphaned::set(false),
+                                ],
+                            )
+                            .exec()
+                            .await?;
+
+                        results.push(result);
+                    }
+                    None => {
+                        let order = get_midpoint_string(last_order.as_str(), "");
+
+                        let result = client
+                            .command()
+                            .create(
+                                definition.name.clone(),
+                                cwd,
+                                definition.command,
+                                order.clone(),
+                                vec![
+                                    command::env::set(env),
+                                    command::origin::set(Some(origin.clone())),
+                                    command::origin_name::set(Some(definition.name)),
+                                ],
+                            )
+                            .exec()
+                            .await?;
+
+                        last_order = order;
+                        results.push(result);
+                    }
+                }
+            }
+
+            for linked in linked_commands {
+                let still_defined = linked
+                    .origin_name
+                    .as_ref()
+                    .map(|name| seen_names.contains(name))
+                    .unwrap_or(false);
+
+                if !still_defined && !linked.orphaned {
+                    let result = client
+                        .command()
+                        .update(
+                            command::id::equals(linked.id),
+                            vec![command::orphaned::set(true)],
+                        )
+                        .exec()
+                        .await?;
+
+                    results.push(result);
+                }
+            }
+
+            Ok::<Vec<command::Data>, AppCommandError>(results)
+        })
+        .await?;
+
+    for command in results.iter() {
+        send_command_update_event(app, command.id)?;
+    }
+
+    Ok(results)
+}
+
+// Keeps the commands linked to launchpane.toml files in sync while the app is running
+pub struct ProjectConfigWatcher {
+    watcher: Mutex<RecommendedWatcher>,
+    watched_dirs: Mutex<HashSet<PathBuf>>,
+}
+
+impl ProjectConfigWatcher {
+    pub fn new<R: Runtime>(
+        client: Arc<PrismaClient>,
+        app_handle: Arc<AppHandle<R>>,
+    ) -> Result<Self, AppCommandError> {
+        let (sender, receiver) = unbounded_channel();
+
+        let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            match event {
+                Ok(event) => {
+                    for path in event.paths {
+                        if path.file_name().map(|n| n == PROJECT_CONFIG_FILE_NAME) == Some(true) {
+                            // Only fails when the app is shutting down
+                            let _ = sender.send(path);
+                        }
+                    }
+                }
+                Err(err) => error!("Error when watching project config: {}", err),
+            }
+        })?;
+
+        tauri::async_runtime::spawn(sync_changed_configs(client, app_handle, receiver));
+
+        Ok(Self {
+            watcher: Mutex::new(watcher),
+            watched_dirs: Mutex::new(HashSet::new()),
+        })
+    }
+
+    // The directory is watched instead of the file, so it still works when editors replace the
+    // file instead of writing to it, or when it's deleted and created again
+    pub fn watch(&self, config_path: &Path) -> Result<(), AppCommandError> {
+        let dir = match config_path.parent() {
+            Some(dir) => dir.to_path_buf(),
+            None => return Ok(()),
+        };
+
+        let mut watched_dirs = self.watched_dirs.lock()?;
+
+        if watched_dirs.contains(&dir) {
+            return Ok(());
+        }
+
+        self.watcher
+            .lock()?
+            .watch(&dir, RecursiveMode::NonRecursive)?;
+
+        debug!(
+            "Watching {} for project config changes",
+            dir.to_string_lossy()
+        );
+
+        watched_dirs.insert(dir);
+
+        Ok(())
+    }
+}
+
+async fn sync_changed_configs<R: Runtime>(
+    client: Arc<PrismaClient>,
+    app_handle: Arc<AppHandle<R>>,
+    mut receiver: UnboundedReceiver<PathBuf>,
+) {
+    while let Some(path) = receiver.recv().await {
+        sleep(SYNC_DEBOUNCE).await;
+
+        let mut changed_paths = HashSet::from([path]);
+
+        while let Ok(path) = receiver.try_recv() {
+            changed_paths.insert(path);
+        }
+
+        for path in changed_paths {
+            debug!("Project config {} changed", path.to_string_lossy());
+
+            if let Err(err) = sync_project_config(&client, &app_handle, &path).await {
+                error!(
+                    "Failed to sync {}: {}",
+                    path.to_string_lossy(),
+                    serde_json::to_string(&err).unwrap_or_default()
+                );
+            }
+        }
+    }
+}
+
+// Links the launchpane.toml in the directory, and keeps watching it for changes
+pub async fn scan_project_directory<R: Runtime>(
+    client: &PrismaClient,
+    app: &AppHandle<R>,
+    watcher: &ProjectConfigWatcher,
+    directory: &str,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    let config_path = canonical_config_path(&Path::new(directory).join(PROJECT_CONFIG_FILE_NAME));
+
+    if !config_path.is_file() {
+        return Err(AppCommandError::ClientError(
+            ClientError::ProjectConfigNotFound,
+        ));
+    }
+
+    let results = sync_project_config(client, app, &config_path).await?;
+
+    watcher.watch(&config_path)?;
+
+    Ok(results)
+}
+
+// Used when the file can't be synced, so its commands don't look like they're still up to date
+async fn orphan_commands<R: Runtime>(
+    client: &PrismaClient,
+    app: &AppHandle<R>,
+    origin: &str,
+) -> Result<(), AppCommandError> {
+    let linked_commands = client
+        .command()
+        .find_many(vec![
+            command::origin::equals(Some(origin.into())),
+            command::orphaned::equals(false),
+        ])
+        .exec()
+        .await?;
+
+    for linked in linked_commands {
+        client
+            .command()
+            .update(
+                command::id::equals(linked.id),
+                vec![command::orphaned::set(true)],
+            )
+            .exec()
+            .await?;
+
+        send_command_update_event(app, linked.id)?;
+    }
+
+    Ok(())
+}
+
+// Catches up with changes made while the app was closed, and watches every linked file again.
+// A file that fails to sync doesn't stop the others.
+pub async fn resume_project_configs<R: Runtime>(
+    client: &PrismaClient,
+    app: &AppHandle<R>,
+    watcher: &ProjectConfigWatcher,
+) -> Result<(), AppCommandError> {
+    let origins: HashSet<String> = client
+        .command()
+        .find_many(vec![command::origin::not(None)])
+        .exec()
+        .await?
+        .into_iter()
+        .filter_map(|c| c.origin)
+        .collect();
+
+    for origin in origins {
+        let config_path = canonical_config_path(Path::new(&origin));
+        let canonical_origin = config_path.to_string_lossy().to_string();
+
+        // Linked before origins were canonical
+        if canonical_origin != origin {
+            client
+                .command()
+                .update_many(
+                    vec![command::origin::equals(Some(origin.clone()))],
+                    vec![command::origin::set(Some(canonical_origin.clone()))],
+                )
+                .exec()
+                .await?;
+        }
+
+        if let Err(err) = sync_project_config(client, app, &config_path).await {
+            error!(
+                "Failed to sync {}: {}",
+                canonical_origin,
+                serde_json::to_string(&err).unwrap_or_default()
+            );
+
+            if let Err(err) = orphan_commands(client, app, &canonical_origin).await {
+                error!(
+                    "Failed to orphan the commands of {}: {}",
+                    canonical_origin,
+                    serde_json::to_string(&err).unwrap_or_default()
+                );
+            }
+        }
+
+        if let Err(err) = watcher.watch(&config_path) {
+            // The directory itself might be gone, the commands are orphaned by the sync above
+            warn!(
+                "Failed to watch {}: {}",
+                config_path.to_string_lossy(),
+                serde_json::to_string(&err).unwrap_or_default()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use rand::Rng;
+    use tauri::test::mock_app;
+
+    use super::*;
+    use crate::test_utils::TestDatabase;
+
+    fn create_project_dir() -> PathBuf {
+        let dir = temp_dir().join(format!(
+            "launchpane-project-{}",
+            rand::thread_rng().gen::<u64>()
+        ));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    async fn linked_commands(client: &PrismaClient) -> Vec<command::Data> {
+        client
+            .command()
+            .find_many(vec![command::origin::not(None)])
+            .order_by(command::id::order(Direction::Asc))
+            .exec()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn creates_and_updates_linked_commands() {
+        let client = TestDatabase::new().await;
+        let app = mock_app();
+        let dir = create_project_dir();
+        let config_path = dir.join(PROJECT_CONFIG_FILE_NAME);
+
+        fs::write(
+            &config_path,
+            "[[commands]]\nname = \"api\"\ncommand = \"npm run dev\"\ncwd = \"api\"\n",
+        )
+        .unwrap();
+
+        let created = sync_project_config(&client, &app.handle(), &config_path)
+            .await
+            .unwrap();
+
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].command, "npm run dev");
+        assert_eq!(
+            created[0].origin.as_deref(),
+            Some(
+                canonical_config_path(&config_path)
+                    .to_string_lossy()
+                    .as_ref()
+            )
+        );
+
+        // Nothing changed, nothing to update
+        let unchanged = sync_project_config(&client, &app.handle(), &config_path)
+            .await
+            .unwrap();
+
+        assert!(unchanged.is_empty());
+
+        fs::write(
+            &config_path,
+            "[[commands]]\nname = \"api\"\ncommand = \"npm start\"\ncwd = \"api\"\n",
+        )
+        .unwrap();
+
+        // Through a different path to the same file
+        let other_path = dir
+            .join("..")
+            .join(dir.file_name().unwrap())
+            .join(PROJECT_CONFIG_FILE_NAME);
+
+        let updated = sync_project_config(&client, &app.handle(), &other_path)
+            .await
+            .unwrap();
+
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].id, created[0].id);
+
+        let commands = linked_commands(&client).await;
+
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].command, "npm start");
+    }
+
+    #[tokio::test]
+    async fn orphans_and_relinks_removed_commands() {
+        let client = TestDatabase::new().await;
+        let app = mock_app();
+        let dir = create_project_dir();
+        let config_path = dir.join(PROJECT_CONFIG_FILE_NAME);
+        let config = "[[commands]]\nname = \"api\"\ncommand = \"npm run dev\"\n\n[[commands]]\nname = \"worker\"\ncommand = \"cargo run\"\n";
+
+        fs::write(&config_path, config).unwrap();
+
+        sync_project_config(&client, &app.handle(), &config_path)
+            .await
+            .unwrap();
+
+        fs::write(
+            &config_path,
+            "[[commands]]\nname = \"api\"\ncommand = \"npm run dev\"\n",
+        )
+        .unwrap();
+
+        sync_project_config(&client, &app.handle(), &config_path)
+            .await
+            .unwrap();
+
+        let commands = linked_commands(&client).await;
+
+        assert_eq!(commands.len(), 2);
+        assert!(!commands[0].orphaned);
+        assert!(commands[1].orphaned);
+
+        // A deleted file orphans everything
+        fs::remove_file(&config_path).unwrap();
+
+        sync_project_config(&client, &app.handle(), &config_path)
+            .await
+            .unwrap();
+
+        assert!(linked_commands(&client)
+            .await
+            .iter()
+            .all(|command| command.orphaned));
+
+        // Defining them again links the same rows
+        fs::write(&config_path, config).unwrap();
+
+        let relinked = sync_project_config(&client, &app.handle(), &config_path)
+            .await
+            .unwrap();
+
+        assert_eq!(relinked.len(), 2);
+
+        let relinked_commands = linked_commands(&client).await;
+
+        assert_eq!(
+            relinked_commands.iter().map(|c| c.id).collect::<Vec<_>>(),
+            commands.iter().map(|c| c.id).collect::<Vec<_>>()
+        );
+        assert!(relinked_commands.iter().all(|command| !command.orphaned));
+    }
+}
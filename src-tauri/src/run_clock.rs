@@ -0,0 +1,80 @@
+// Where log lines go in the log of their command, shared with the CLI so the lines it stores are
+// ordered the same way
+
+use std::time::{Instant, SystemTime, SystemTimeError};
+
+use prisma_client_rust::{Direction, QueryError};
+
+use crate::prisma::{command_log_line, PrismaClient};
+
+pub fn timestamp() -> Result<f64, SystemTimeError> {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_nanos();
+    Ok(nanos as f64 / 1000000.0)
+}
+
+// Lines are ordered by run and sequence, the timestamp is only for showing
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LineStamp {
+    pub run: i32,
+    pub sequence: i32,
+    pub timestamp: f64,
+}
+
+// Stamps the lines of the current run of a command. The time is measured with a monotonic clock
+// from the start of the run, so lines stay in order when the wall clock jumps.
+pub struct RunClock {
+    pub run: i32,
+    next_sequence: i32,
+    started_at: Instant,
+    started_at_wall: f64,
+    last_timestamp: f64,
+}
+
+impl RunClock {
+    pub fn new(run: i32, next_sequence: i32) -> Result<Self, SystemTimeError> {
+        Ok(Self {
+            run,
+            next_sequence,
+            started_at: Instant::now(),
+            started_at_wall: timestamp()?,
+            last_timestamp: 0.0,
+        })
+    }
+
+    // For a line that arrived at `arrived_at` but is only stored now. It still goes after every
+    // line stamped before, and its time is moved up so it doesn't go backwards.
+    pub fn tick_at(&mut self, arrived_at: Instant) -> LineStamp {
+        let elapsed = arrived_at.saturating_duration_since(self.started_at);
+        let timestamp =
+            (self.started_at_wall + elapsed.as_secs_f64() * 1000.0).max(self.last_timestamp);
+
+        let stamp = LineStamp {
+            run: self.run,
+            sequence: self.next_sequence,
+            timestamp,
+        };
+
+        self.next_sequence += 1;
+        self.last_timestamp = timestamp;
+
+        stamp
+    }
+}
+
+// The run and next sequence number of the latest stored line of the command
+pub async fn load_latest_run(
+    client: &PrismaClient,
+    command_id: i32,
+) -> Result<(i32, i32), QueryError> {
+    let latest = client
+        .command_log_line()
+        .find_first(vec![command_log_line::command_id::equals(command_id)])
+        .order_by(command_log_line::run::order(Direction::Desc))
+        .order_by(command_log_line::sequence::order(Direction::Desc))
+        .exec()
+        .await?;
+
+    Ok(latest.map_or((0, 0), |line| (line.run, line.sequence + 1)))
+}
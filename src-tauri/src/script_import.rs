@@ -0,0 +1,208 @@
+use std::{fs, path::Path};
+
+use prisma_client_rust::Direction;
+use serde::Deserialize;
+use specta::Type;
+
+use crate::{
+    errors::AppCommandError,
+    prisma::{_prisma::PrismaClient, command},
+    utils::get_midpoint_string,
+};
+
+#[derive(Debug, Deserialize, Type, Clone, Copy)]
+pub enum ScriptImportSource {
+    Procfile,
+    PackageJson,
+}
+
+impl ScriptImportSource {
+    fn file_name(&self) -> &'static str {
+        match self {
+            ScriptImportSource::Procfile => "Procfile",
+            ScriptImportSource::PackageJson => "package.json",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ImportedScript {
+    pub name: String,
+    pub command: String,
+}
+
+// Each process is a `name: command` line, everything else is ignored
+pub fn parse_procfile(content: &str) -> Vec<ImportedScript> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+
+            if line.starts_with('#') {
+                return None;
+            }
+
+            let (name, command) = line.split_once(':')?;
+            let (name, command) = (name.trim(), command.trim());
+
+            let is_valid_name = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+            if !is_valid_name || command.is_empty() {
+                return None;
+            }
+
+            Some(ImportedScript {
+                name: name.into(),
+                command: command.into(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct PackageJson {
+    // Keeps the order of the file, which is usually the order the scripts make sense in
+    #[serde(default)]
+    scripts: serde_json::Map<String, serde_json::Value>,
+}
+
+// Use whichever package manager the project's lockfile belongs to
+fn detect_package_manager(directory: &Path) -> &'static str {
+    if directory.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if directory.join("yarn.lock").exists() {
+        "yarn"
+    } else if directory.join("bun.lockb").exists() {
+        "bun"
+    } else {
+        "npm"
+    }
+}
+
+pub fn parse_package_json_scripts(
+    content: &str,
+    package_manager: &str,
+) -> Result<Vec<ImportedScript>, AppCommandError> {
+    let package_json: PackageJson =
+        serde_json::from_str(content).map_err(|e| AppCommandError::ConfigError(e.to_string()))?;
+
+    Ok(package_json
+        .scripts
+        .into_iter()
+        .filter(|(_, script)| script.is_string())
+        .map(|(name, _)| ImportedScript {
+            command: format!("{} run {}", package_manager, name),
+            name,
+        })
+        .collect())
+}
+
+// Creates a command for every script in the directory's Procfile or package.json, after the
+// existing commands
+pub async fn import_scripts(
+    client: &PrismaClient,
+    directory: &str,
+    source: ScriptImportSource,
+) -> Result<Vec<command::Data>, AppCommandError> {
+    let directory = Path::new(directory);
+    let content = fs::read_to_string(directory.join(source.file_name()))?;
+
+    let scripts = match source {
+        ScriptImportSource::Procfile => parse_procfile(&content),
+        ScriptImportSource::PackageJson => {
+            parse_package_json_scripts(&content, detect_package_manager(directory))?
+        }
+    };
+
+    let cwd = directory.to_string_lossy().to_string();
+
+    client
+        ._transaction()
+        .run(|client| async move {
+            let mut last_order = client
+                .command()
+                .find_first(vec![])
+                .order_by(command::order::order(Direction::Desc))
+                .exec()
+                .await?
+                .map(|c| c.order)
+                .unwrap_or_default();
+
+            let mut results = vec![];
+
+            for script in scripts {
+                let order = get_midpoint_string(last_order.as_str(), "");
+
+                let result = client
+                    .command()
+                    .create(
+                        script.name,
+                        cwd.clone(),
+                        script.command,
+                        order.clone(),
+                        vec![],
+                    )
+                    .exec()
+                    .await?;
+
+                last_order = order;
+                results.push(result);
+            }
+
+            Ok::<Vec<command::Data>, AppCommandError>(results)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_procfile() {
+        assert_eq!(
+            parse_procfile(
+                "# Dev processes\nweb: bundle exec rails s -p $PORT\n\nworker:sidekiq -C config.yml\nnot a process\nempty:\n"
+            ),
+            vec![
+                ImportedScript {
+                    name: "web".into(),
+                    command: "bundle exec rails s -p $PORT".into(),
+                },
+                ImportedScript {
+                    name: "worker".into(),
+                    command: "sidekiq -C config.yml".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_package_json_scripts() {
+        assert_eq!(
+            parse_package_json_scripts(
+                r#"{ "name": "app", "scripts": { "dev": "vite dev", "build": "vite build", "x": 1 } }"#,
+                "pnpm"
+            )
+            .unwrap(),
+            vec![
+                ImportedScript {
+                    name: "dev".into(),
+                    command: "pnpm run dev".into(),
+                },
+                ImportedScript {
+                    name: "build".into(),
+                    command: "pnpm run build".into(),
+                },
+            ]
+        );
+
+        assert_eq!(
+            parse_package_json_scripts(r#"{ "name": "no-scripts" }"#, "npm").unwrap(),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+#[cfg(target_family = "windows")]
+use async_process::windows::CommandExt;
+
+use std::collections::HashMap;
+
+use async_process::Command;
+
+use crate::prisma::command;
+
+#[cfg(target_family = "windows")]
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+// The program used to run a command, stored as a string in `Command.shell`
+#[derive(Debug, PartialEq)]
+pub enum Shell {
+    // bash on unix, cmd or powershell on windows
+    Default,
+    Bash,
+    Sh,
+    Zsh,
+    Fish,
+    // Split the command into arguments and run it without any shell
+    Direct,
+    // Any other interpreter that accepts `-c <command>`, e.g. /usr/local/bin/nu
+    Custom(String),
+}
+
+impl Shell {
+    pub fn from_command(command: &command::Data) -> Result<Self, String> {
+        match command.shell.as_deref() {
+            None | Some("") | Some("default") => Ok(Shell::Default),
+            Some("bash") => Ok(Shell::Bash),
+            Some("sh") => Ok(Shell::Sh),
+            Some("zsh") => Ok(Shell::Zsh),
+            Some("fish") => Ok(Shell::Fish),
+            Some("direct") => Ok(Shell::Direct),
+            Some("custom") => match command.shell_path.as_deref() {
+                Some(path) if !path.is_empty() => Ok(Shell::Custom(path.into())),
+                _ => Err("No interpreter path set for custom shell".into()),
+            },
+            Some(other) => Err(format!("Unknown shell `{}`", other)),
+        }
+    }
+
+    fn program(&self) -> Option<&str> {
+        match self {
+            Shell::Default => None,
+            Shell::Bash => Some("bash"),
+            Shell::Sh => Some("sh"),
+            Shell::Zsh => Some("zsh"),
+            Shell::Fish => Some("fish"),
+            Shell::Direct => None,
+            Shell::Custom(path) => Some(path.as_str()),
+        }
+    }
+}
+
+pub struct ShellOptions {
+    // Run as a login shell, so profile files like `.zprofile` are loaded
+    pub login: bool,
+    // Run as an interactive shell, so rc files like `.zshrc` are loaded
+    pub interactive: bool,
+}
+
+impl ShellOptions {
+    pub fn from_command(command: &command::Data) -> Self {
+        Self {
+            login: command.shell_login,
+            interactive: command.shell_interactive,
+        }
+    }
+}
+
+// Returns the program and its arguments to run `command_line` with the given shell
+pub fn shell_invocation(
+    shell: &Shell,
+    options: &ShellOptions,
+    command_line: &str,
+) -> Result<(String, Vec<String>), String> {
+    if *shell == Shell::Direct {
+        let mut args = shell_words::split(command_line)
+            .map_err(|e| format!("Failed to parse command: {}", e))?;
+
+        if args.is_empty() {
+            return Err("Command is empty".into());
+        }
+
+        let program = args.remove(0);
+
+        return Ok((program, args));
+    }
+
+    let program = shell.program().unwrap_or("bash");
+
+    let mut args = vec![];
+
+    if options.login {
+        args.push("-l".into());
+    }
+
+    if options.interactive {
+        args.push("-i".into());
+    }
+
+    args.push("-c".into());
+    args.push(command_line.into());
+
+    Ok((program.into(), args))
+}
+
+// Extra variables set on the command, stored as a JSON object in `Command.env`
+pub fn command_env(command: &command::Data) -> Result<HashMap<String, String>, String> {
+    match command.env.as_deref() {
+        None | Some("") => Ok(HashMap::new()),
+        Some(env) => {
+            serde_json::from_str(env).map_err(|e| format!("Invalid environment variables: {}", e))
+        }
+    }
+}
+
+// `base_environment` goes under the command's own variables, so those always win
+pub fn build_command(
+    command: &command::Data,
+    base_environment: Option<&HashMap<String, String>>,
+) -> Result<Command, String> {
+    let mut cmd = build_shell_command(command)?;
+
+    if let Some(variables) = base_environment {
+        cmd.envs(variables);
+    }
+
+    cmd.envs(command_env(command)?);
+
+    Ok(cmd)
+}
+
+fn build_shell_command(command: &command::Data) -> Result<Command, String> {
+    let shell = Shell::from_command(command)?;
+    let options = ShellOptions::from_command(command);
+
+    #[cfg(target_family = "windows")]
+    {
+        if shell == Shell::Default {
+            let cmd =
+                // Powershell is significantly slower, but you have to use it to run commands on
+                // these kinds of paths
+                if command.cwd.starts_with("\\\\") {
+                    let mut cmd = Command::new("powershell");
+                    cmd.arg("-Command");
+                    cmd.arg(command.command.clone());
+
+                    cmd.creation_flags(CREATE_NO_WINDOW);
+
+                    cmd
+                } else {
+                    let mut cmd = Command::new("cmd");
+                    cmd.arg("/s");
+                    cmd.arg("/c");
+
+                    cmd.raw_arg(command.command.clone());
+
+                    cmd.creation_flags(CREATE_NO_WINDOW);
+
+                    cmd
+                };
+
+            return Ok(cmd);
+        }
+    }
+
+    let (program, args) = shell_invocation(&shell, &options, &command.command)?;
+
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+
+    #[cfg(target_family = "windows")]
+    cmd.creation_flags(CREATE_NO_WINDOW);
+
+    Ok(cmd)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NO_OPTIONS: ShellOptions = ShellOptions {
+        login: false,
+        interactive: false,
+    };
+
+    #[test]
+    fn test_shell_invocation() {
+        assert_eq!(
+            shell_invocation(&Shell::Zsh, &NO_OPTIONS, "npm run dev").unwrap(),
+            ("zsh".into(), vec!["-c".into(), "npm run dev".into()])
+        );
+
+        assert_eq!(
+            shell_invocation(
+                &Shell::Fish,
+                &ShellOptions {
+                    login: true,
+                    interactive: true,
+                },
+                "ll"
+            )
+            .unwrap(),
+            (
+                "fish".into(),
+                vec!["-l".into(), "-i".into(), "-c".into(), "ll".into()]
+            )
+        );
+
+        assert_eq!(
+            shell_invocation(&Shell::Custom("/opt/bin/nu".into()), &NO_OPTIONS, "ls").unwrap(),
+            ("/opt/bin/nu".into(), vec!["-c".into(), "ls".into()])
+        );
+    }
+
+    #[test]
+    fn test_direct_invocation() {
+        assert_eq!(
+            shell_invocation(&Shell::Direct, &NO_OPTIONS, "node 'my server.js' --port 80").unwrap(),
+            (
+                "node".into(),
+                vec!["my server.js".into(), "--port".into(), "80".into()]
+            )
+        );
+
+        assert!(shell_invocation(&Shell::Direct, &NO_OPTIONS, "  ").is_err());
+        assert!(shell_invocation(&Shell::Direct, &NO_OPTIONS, "echo 'unclosed").is_err());
+    }
+}
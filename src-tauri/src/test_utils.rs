@@ -0,0 +1,48 @@
+use std::{env::temp_dir, fs, ops::Deref, path::PathBuf, sync::Arc};
+
+use rand::Rng;
+
+use crate::prisma::PrismaClient;
+
+// A migrated database in a temporary file, which is removed again when this is dropped
+pub struct TestDatabase {
+    pub client: Arc<PrismaClient>,
+    path: PathBuf,
+}
+
+impl TestDatabase {
+    pub async fn new() -> Self {
+        let path = temp_dir().join(format!(
+            "launchpane-test-{}.db",
+            rand::thread_rng().gen::<u64>()
+        ));
+
+        let client = PrismaClient::_builder()
+            .with_url(format!("file:{}", path.to_string_lossy()))
+            .build()
+            .await
+            .unwrap();
+
+        client._migrate_deploy().await.unwrap();
+
+        Self {
+            client: Arc::new(client),
+            path,
+        }
+    }
+}
+
+impl Deref for TestDatabase {
+    type Target = PrismaClient;
+
+    fn deref(&self) -> &PrismaClient {
+        &self.client
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        let _ = fs::remove_file(self.path.with_extension("db-journal"));
+    }
+}
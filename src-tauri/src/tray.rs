@@ -0,0 +1,174 @@
+// The system tray lets commands be started and stopped while the main window is hidden.
+
+use log::error;
+use tauri::{
+    async_runtime::spawn, AppHandle, CustomMenuItem, Manager, SystemTray, SystemTrayEvent,
+    SystemTrayMenu, SystemTrayMenuItem, SystemTraySubmenu,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::{sleep, Duration},
+};
+
+use crate::{errors::AppCommandError, process::ProcessStatus};
+
+const MAIN_WINDOW_LABEL: &str = "main";
+
+const SHOW_ITEM_ID: &str = "show";
+const QUIT_ITEM_ID: &str = "quit";
+const START_ITEM_PREFIX: &str = "start-";
+const STOP_ITEM_PREFIX: &str = "stop-";
+
+// Several commands often change at once, e.g. when reordering, rebuild the menu only once for them
+const REBUILD_DEBOUNCE: Duration = Duration::from_millis(100);
+
+pub fn create_system_tray() -> SystemTray {
+    SystemTray::new().with_menu(add_app_items(SystemTrayMenu::new()))
+}
+
+fn add_app_items(menu: SystemTrayMenu) -> SystemTrayMenu {
+    menu.add_item(CustomMenuItem::new(SHOW_ITEM_ID, "Show Launchpane"))
+        .add_item(CustomMenuItem::new(QUIT_ITEM_ID, "Quit"))
+}
+
+fn status_indicator(status: ProcessStatus) -> &'static str {
+    match status {
+        ProcessStatus::Running { .. } => "●",
+        ProcessStatus::Stopping => "◐",
+        ProcessStatus::Stopped => "○",
+    }
+}
+
+async fn build_menu(app: &AppHandle) -> Result<SystemTrayMenu, AppCommandError> {
+    let commands = crate::get_commands(app.state()).await?;
+    let mut menu = SystemTrayMenu::new();
+
+    for command in &commands {
+        let status = crate::get_process_status(app.state(), command.id).await?;
+
+        let mut start_item =
+            CustomMenuItem::new(format!("{}{}", START_ITEM_PREFIX, command.id), "Start");
+        let mut stop_item =
+            CustomMenuItem::new(format!("{}{}", STOP_ITEM_PREFIX, command.id), "Stop");
+
+        if let ProcessStatus::Stopped = status {
+            stop_item = stop_item.disabled();
+        } else {
+            start_item = start_item.disabled();
+        }
+
+        let title = if command.name.is_empty() {
+            &command.command
+        } else {
+            &command.name
+        };
+
+        menu = menu.add_submenu(SystemTraySubmenu::new(
+            format!("{} {}", status_indicator(status), title),
+            SystemTrayMenu::new()
+                .add_item(start_item)
+                .add_item(stop_item),
+        ));
+    }
+
+    if !commands.is_empty() {
+        menu = menu.add_native_item(SystemTrayMenuItem::Separator);
+    }
+
+    Ok(add_app_items(menu))
+}
+
+async fn rebuild_on_request(app: AppHandle, mut receiver: UnboundedReceiver<()>) {
+    while receiver.recv().await.is_some() {
+        sleep(REBUILD_DEBOUNCE).await;
+        while receiver.try_recv().is_ok() {}
+
+        let result = match build_menu(&app).await {
+            Ok(menu) => app
+                .tray_handle()
+                .set_menu(menu)
+                .map_err(AppCommandError::from),
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            error!(
+                "Failed to rebuild tray menu: {}",
+                serde_json::to_string(&err).unwrap_or_default()
+            );
+        }
+    }
+}
+
+// Rebuilds the tray menu in the background whenever it's requested
+pub struct TrayMenuUpdater {
+    sender: UnboundedSender<()>,
+}
+
+impl TrayMenuUpdater {
+    pub fn new(app: AppHandle) -> Self {
+        let (sender, receiver) = unbounded_channel();
+        spawn(rebuild_on_request(app, receiver));
+
+        let updater = Self { sender };
+        updater.request_rebuild();
+        updater
+    }
+
+    pub fn request_rebuild(&self) {
+        let _ = self.sender.send(());
+    }
+}
+
+pub fn show_main_window(app: &AppHandle) {
+    let Some(window) = app.get_window(MAIN_WINDOW_LABEL) else {
+        return;
+    };
+
+    if let Err(err) = window
+        .show()
+        .and_then(|_| window.unminimize())
+        .and_then(|_| window.set_focus())
+    {
+        error!("Failed to show main window: {}", err);
+    }
+}
+
+pub fn is_main_window(label: &str) -> bool {
+    label == MAIN_WINDOW_LABEL
+}
+
+pub fn handle_tray_event(app: &AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_main_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+            SHOW_ITEM_ID => show_main_window(app),
+            QUIT_ITEM_ID => app.exit(0),
+            id => {
+                let start_id = id
+                    .strip_prefix(START_ITEM_PREFIX)
+                    .and_then(|id| id.parse().ok());
+                let stop_id = id
+                    .strip_prefix(STOP_ITEM_PREFIX)
+                    .and_then(|id| id.parse().ok());
+
+                let app = app.clone();
+                spawn(async move {
+                    let result = match (start_id, stop_id) {
+                        (Some(command_id), _) => crate::run_process(app.state(), command_id).await,
+                        (_, Some(command_id)) => crate::kill_process(app.state(), command_id).await,
+                        _ => Ok(()),
+                    };
+
+                    if let Err(err) = result {
+                        error!(
+                            "Failed to handle tray menu click: {}",
+                            serde_json::to_string(&err).unwrap_or_default()
+                        );
+                    }
+                });
+            }
+        },
+        _ => {}
+    }
+}
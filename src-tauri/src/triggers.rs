@@ -0,0 +1,229 @@
+use log::error;
+use regex::Regex;
+use serde::Deserialize;
+use specta::Type;
+
+use crate::{
+    errors::{AppCommandError, ClientError},
+    prisma::{_prisma::PrismaClient, command, command_trigger},
+    process::CommandLogLineSource,
+};
+
+// Serialized the same way as it's stored in the DB
+#[derive(Debug, Deserialize, Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum TriggerActionKind {
+    StartCommand,
+    StopCommand,
+    MarkFailed,
+}
+
+impl TriggerActionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerActionKind::StartCommand => "startCommand",
+            TriggerActionKind::StopCommand => "stopCommand",
+            TriggerActionKind::MarkFailed => "markFailed",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "startCommand" => Some(TriggerActionKind::StartCommand),
+            "stopCommand" => Some(TriggerActionKind::StopCommand),
+            "markFailed" => Some(TriggerActionKind::MarkFailed),
+            _ => None,
+        }
+    }
+}
+
+// Checks that the trigger can be used, and returns the target command to store for it
+pub fn validate_trigger(
+    pattern: &str,
+    action: TriggerActionKind,
+    target_command_id: Option<i32>,
+) -> Result<Option<i32>, AppCommandError> {
+    // An empty pattern would match every line
+    if pattern.is_empty() {
+        return Err(AppCommandError::ClientError(ClientError::MissingPattern));
+    }
+
+    Regex::new(pattern)?;
+
+    match action {
+        TriggerActionKind::StartCommand => {
+            target_command_id
+                .map(Some)
+                .ok_or(AppCommandError::ClientError(
+                    ClientError::MissingTriggerTarget,
+                ))
+        }
+        _ => Ok(None),
+    }
+}
+
+// Triggers on stderr can't be used while the command merges stderr into stdout
+pub async fn validate_trigger_source(
+    db_client: &PrismaClient,
+    command_id: i32,
+    source: Option<i32>,
+) -> Result<(), AppCommandError> {
+    if source != Some(CommandLogLineSource::STDERR as i32) {
+        return Ok(());
+    }
+
+    let command = db_client
+        .command()
+        .find_unique(command::id::equals(command_id))
+        .exec()
+        .await?
+        .ok_or(AppCommandError::ClientError(ClientError::CommandNotFound))?;
+
+    if command.merge_stderr {
+        return Err(AppCommandError::ClientError(ClientError::StderrMerged));
+    }
+
+    Ok(())
+}
+
+pub async fn has_stderr_triggers(
+    db_client: &PrismaClient,
+    command_id: i32,
+) -> Result<bool, AppCommandError> {
+    let count = db_client
+        .command_trigger()
+        .count(vec![
+            command_trigger::command_id::equals(command_id),
+            command_trigger::source::equals(Some(CommandLogLineSource::STDERR as i32)),
+        ])
+        .exec()
+        .await?;
+
+    Ok(count > 0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerAction {
+    StartCommand(i32),
+    StopCommand,
+    MarkFailed,
+}
+
+struct Trigger {
+    pattern: Regex,
+    source: Option<i32>,
+    action: TriggerAction,
+}
+
+// The triggers of a command, loaded when one of its instances starts
+pub struct CommandTriggers {
+    triggers: Vec<Trigger>,
+}
+
+impl CommandTriggers {
+    pub async fn load(db_client: &PrismaClient, command_id: i32) -> Result<Self, AppCommandError> {
+        let rows = db_client
+            .command_trigger()
+            .find_many(vec![command_trigger::command_id::equals(command_id)])
+            .exec()
+            .await?;
+
+        let triggers = rows
+            .into_iter()
+            // Stored before empty patterns were rejected, it would match every line
+            .filter(|row| !row.pattern.is_empty())
+            .filter_map(|row| {
+                let kind = TriggerActionKind::parse(&row.action);
+
+                let action = match (kind, row.target_command_id) {
+                    (Some(TriggerActionKind::StartCommand), Some(target)) => {
+                        TriggerAction::StartCommand(target)
+                    }
+                    (Some(TriggerActionKind::StopCommand), _) => TriggerAction::StopCommand,
+                    (Some(TriggerActionKind::MarkFailed), _) => TriggerAction::MarkFailed,
+                    // The target command was deleted
+                    _ => return None,
+                };
+
+                match Regex::new(&row.pattern) {
+                    Ok(pattern) => Some(Trigger {
+                        pattern,
+                        source: row.source,
+                        action,
+                    }),
+                    Err(err) => {
+                        error!("Skipping invalid trigger pattern {}: {}", row.pattern, err);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Self { triggers })
+    }
+
+    pub fn matching<'a>(
+        &'a self,
+        source: i32,
+        line: &'a str,
+    ) -> impl Iterator<Item = TriggerAction> + 'a {
+        self.triggers
+            .iter()
+            .filter(move |t| t.source.map_or(true, |s| s == source) && t.pattern.is_match(line))
+            .map(|t| t.action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trigger(pattern: &str, source: Option<i32>, action: TriggerAction) -> Trigger {
+        Trigger {
+            pattern: Regex::new(pattern).unwrap(),
+            source,
+            action,
+        }
+    }
+
+    #[test]
+    fn matches_pattern_and_source() {
+        let triggers = CommandTriggers {
+            triggers: vec![
+                trigger(
+                    "compiled successfully",
+                    None,
+                    TriggerAction::StartCommand(2),
+                ),
+                trigger("ERROR", Some(2), TriggerAction::MarkFailed),
+            ],
+        };
+
+        assert_eq!(
+            triggers
+                .matching(1, "webpack compiled successfully in 20ms")
+                .collect::<Vec<_>>(),
+            vec![TriggerAction::StartCommand(2)]
+        );
+        assert_eq!(triggers.matching(1, "ERROR: oops").count(), 0);
+        assert_eq!(
+            triggers.matching(2, "ERROR: oops").collect::<Vec<_>>(),
+            vec![TriggerAction::MarkFailed]
+        );
+    }
+
+    #[test]
+    fn validates_start_command_target() {
+        assert!(validate_trigger("done", TriggerActionKind::StartCommand, None).is_err());
+        assert_eq!(
+            validate_trigger("done", TriggerActionKind::StartCommand, Some(3)).unwrap(),
+            Some(3)
+        );
+        assert_eq!(
+            validate_trigger("done", TriggerActionKind::StopCommand, Some(3)).unwrap(),
+            None
+        );
+        assert!(validate_trigger("(", TriggerActionKind::StopCommand, None).is_err());
+        assert!(validate_trigger("", TriggerActionKind::MarkFailed, None).is_err());
+    }
+}
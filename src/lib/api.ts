@@ -1,5 +1,6 @@
 import * as bindingsApi from './generated/bindings';
-import type { AppEventPayload } from './generated/events';
+import type { AppEventPayload } from './generated/data';
+import type { CommandLogLine, ProcessStatus } from './types';
 import { listen, type Event } from '@tauri-apps/api/event';
 
 export type AppAPIType = typeof bindingsApi;
@@ -48,12 +49,70 @@ export const onDataUpdate = (callback: (data: Event<AppEventPayload>) => void) =
 
 export const onCommandUpdate = (commandId: number, callback: () => void) => 
   onDataUpdate((data) => {
-    if ('CommandUpdateEvent' in data.payload && data.payload.CommandUpdateEvent === commandId)
+    if (
+      typeof data.payload === 'object' &&
+      'CommandUpdateEvent' in data.payload &&
+      data.payload.CommandUpdateEvent === commandId
+    )
       callback();
   });
 
-export const onNewLogLines = (commandId: number, callback: () => void) =>
+// Log events are only sent to windows that subscribed to the command
+const onCommandLogEvent = (
+  commandId: number,
+  callback: (data: Event<AppEventPayload>) => void,
+) => {
+  const remove = onDataUpdate(callback);
+  bindingsApi.subscribeToCommandLogs(commandId);
+
+  return () => {
+    remove();
+    bindingsApi.unsubscribeFromCommandLogs(commandId);
+  };
+};
+
+export const onLogLines = (commandId: number, callback: (logLines: CommandLogLine[]) => void) =>
+  onCommandLogEvent(commandId, (data) => {
+    if (
+      typeof data.payload === 'object' &&
+      'CommandLogLinesEvent' in data.payload &&
+      data.payload.CommandLogLinesEvent.command_id === commandId
+    )
+      callback(data.payload.CommandLogLinesEvent.log_lines);
+  });
+
+export const onLogsCleared = (commandId: number, callback: () => void) =>
+  onCommandLogEvent(commandId, (data) => {
+    if (
+      typeof data.payload === 'object' &&
+      'CommandLogsClearedEvent' in data.payload &&
+      data.payload.CommandLogsClearedEvent === commandId
+    )
+      callback();
+  });
+
+export const onLogLinesDropped = (callback: () => void) =>
+  onDataUpdate((data) => {
+    if (data.payload === 'LogLinesDroppedEvent') callback();
+  });
+
+// Sent to every window, without the lines, so showing activity doesn't need a subscription
+export const onCommandActivity = (commandId: number, callback: () => void) =>
   onDataUpdate((data) => {
-    if ('CommandLogUpdateEvent' in data.payload && data.payload.CommandLogUpdateEvent === commandId)
+    if (
+      typeof data.payload === 'object' &&
+      'CommandActivityEvent' in data.payload &&
+      data.payload.CommandActivityEvent === commandId
+    )
       callback();
   });
+
+export const onProcessStatus = (commandId: number, callback: (status: ProcessStatus) => void) =>
+  onDataUpdate((data) => {
+    if (
+      typeof data.payload === 'object' &&
+      'ProcessStatusEvent' in data.payload &&
+      data.payload.ProcessStatusEvent.command_id === commandId
+    )
+      callback(data.payload.ProcessStatusEvent.status);
+  });
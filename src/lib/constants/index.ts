@@ -6,6 +6,10 @@ import console from '@mdi/svg/svg/console.svg';
 import play from '@mdi/svg/svg/play.svg';
 import stop from '@mdi/svg/svg/stop.svg';
 import closeBox from '@mdi/svg/svg/close-box.svg';
+import openInNew from '@mdi/svg/svg/open-in-new.svg';
+import bell from '@mdi/svg/svg/bell.svg';
+import lightning from '@mdi/svg/svg/lightning-bolt.svg';
+import broom from '@mdi/svg/svg/broom.svg';
 
 export const icons = {
   arrowLeft: [arrowLeft, 'mdi-arrow-left'],
@@ -16,4 +20,8 @@ export const icons = {
   play: [play, 'mdi-play'],
   stop: [stop, 'mdi-stop'],
   closeBox: [closeBox, 'mdi-close-box'],
+  openInNew: [openInNew, 'mdi-open-in-new'],
+  bell: [bell, 'mdi-bell'],
+  lightning: [lightning, 'mdi-lightning-bolt'],
+  broom: [broom, 'mdi-broom'],
 };
@@ -1,24 +1,32 @@
-import { readable } from 'svelte/store';
-import { appAPI, onCommandUpdate, onNewLogLines } from './api';
+import { readable, writable } from 'svelte/store';
+import {
+  appAPI,
+  onCommandActivity,
+  onLogLines,
+  onLogLinesDropped,
+  onLogsCleared,
+  onProcessStatus,
+} from './api';
 import { Mutex } from 'async-mutex';
-import type { ProcessStatus } from './types';
+import type { CommandLogLine, LogLevel, ProcessStatusName } from './types';
+import { getProcessStatusName, isAtOrAboveLevel, mergeLogLines } from './utils';
 import { throttle } from 'lodash-es';
 
 export function createCommandStatusStore(commandId: number) {
-  return readable<ProcessStatus>('Stopped', (set) => {
+  return readable<ProcessStatusName>('Stopped', (set) => {
     const mutex = new Mutex();
     console.debug('Subscribed to command status store', commandId);
     function updateStatus() {
       mutex.runExclusive(async () => {
         const status = await appAPI().getProcessStatus(commandId);
-        set(status);
+        set(getProcessStatusName(status));
       });
     }
 
     updateStatus();
 
-    const remove = onCommandUpdate(commandId, () => {
-      updateStatus();
+    const remove = onProcessStatus(commandId, (status) => {
+      mutex.runExclusive(() => set(getProcessStatusName(status)));
     });
     return () => {
       console.debug('Removed command status store', commandId);
@@ -37,7 +45,7 @@ export function createRecentActivityStore(commandId: number) {
 
     let isAlreadyRunning = false;
 
-    const remove = onNewLogLines(commandId, async () => {
+    const remove = onCommandActivity(commandId, async () => {
       if (isAlreadyRunning) return;
       isAlreadyRunning = true;
       set(true);
@@ -52,3 +60,90 @@ export function createRecentActivityStore(commandId: number) {
     };
   });
 }
+
+export function getLogLinesStore(
+  commandId: number,
+  initialCommandLogLines: CommandLogLine[],
+  minLevel: LogLevel | null = null,
+) {
+  console.debug('Created log lines store', commandId);
+  let firstLogId = 0;
+
+  const mutex = new Mutex();
+
+  const store = writable(initialCommandLogLines as CommandLogLine[], (set, update) => {
+    console.debug('Subscribed to log lines store', commandId);
+
+    let lastLogId = 0;
+
+    function updateWithNewLogs() {
+      mutex.runExclusive(async () => {
+        const newLog = await appAPI().getNewerCommandLogLines(commandId, lastLogId, minLevel);
+        if (newLog.length > 0) {
+          if (firstLogId === 0) firstLogId = newLog[0].id;
+          lastLogId = Math.max(lastLogId, ...newLog.map((line) => line.id));
+          update((current) => mergeLogLines(current, newLog));
+        }
+      });
+    }
+
+    set(initialCommandLogLines as CommandLogLine[]);
+
+    if (initialCommandLogLines.length > 0) {
+      firstLogId = initialCommandLogLines[0].id;
+      lastLogId = Math.max(...initialCommandLogLines.map((line) => line.id));
+      updateWithNewLogs();
+    }
+
+    const removeLogLines = onLogLines(commandId, (logLines) => {
+      mutex.runExclusive(() => {
+        // Lines that were already fetched while the event was on its way are skipped
+        const unseenLog = logLines.filter((line) => line.id > lastLogId);
+        if (unseenLog.length === 0) return;
+        lastLogId = Math.max(lastLogId, ...unseenLog.map((line) => line.id));
+
+        const newLog = unseenLog.filter((line) => isAtOrAboveLevel(line.level, minLevel));
+        if (newLog.length > 0) {
+          if (firstLogId === 0) firstLogId = newLog[0].id;
+          update((current) => mergeLogLines(current, newLog));
+        }
+      });
+    });
+    const removeLogLinesDropped = onLogLinesDropped(() => updateWithNewLogs());
+
+    // Logs can be cleared from any window, the store starts over empty
+    const removeLogsCleared = onLogsCleared(commandId, () => {
+      mutex.runExclusive(() => {
+        firstLogId = 0;
+        lastLogId = 0;
+        set([]);
+      });
+    });
+
+    return () => {
+      console.debug('Removed log lines store', commandId);
+      removeLogLines();
+      removeLogLinesDropped();
+      removeLogsCleared();
+    };
+  });
+
+  function loadMore() {
+    console.debug('Loading more logs, from', firstLogId)
+    if (!firstLogId) return;
+
+    mutex.runExclusive(async () => {
+      const newLog = await appAPI().getOlderCommandLogLines(commandId, firstLogId, minLevel);
+      if (newLog.length > 0) {
+        if (firstLogId === 0) firstLogId = newLog[0].id;
+        firstLogId = newLog[0].id;
+        store.update((current) => [...newLog, ...current]);
+      }
+    });
+  }
+
+  return {
+    subscribe: store.subscribe,
+    loadMore,
+  };
+}
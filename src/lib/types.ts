@@ -1,8 +1,20 @@
-export type { Command, CommandLogLine, ProcessStatus } from './generated/bindings';
+export type {
+  Command,
+  CommandLogLine,
+  CommandNotificationRule,
+  CommandTrigger,
+  LogLevel,
+  NotificationRuleKind,
+  ProcessStatus,
+  TriggerActionKind,
+} from './generated/bindings';
+
+export type ProcessStatusName = 'Running' | 'Stopping' | 'Stopped';
 
 export enum WindowState {
   List = 0,
   Editing = 1,
+  Console = 2,
 }
 
 export enum CommandLineSource {
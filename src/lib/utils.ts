@@ -1,5 +1,5 @@
 import { getPlatformDetails } from './platformData';
-import type { Command } from './types';
+import type { Command, CommandLogLine, LogLevel, ProcessStatus, ProcessStatusName } from './types';
 
 export function getCommandDescriptor(command: Command) {
   if (!command.command) return '...';
@@ -10,6 +10,38 @@ export function getCommandDescriptor(command: Command) {
   return `${lastCWDDir}> ${command.command}`;
 }
 
+export function getProcessStatusName(status: ProcessStatus): ProcessStatusName {
+  return typeof status === 'string' ? status : 'Running';
+}
+
 export function showCommandTitleWithMonospace(command: Command) {
   return !!(command.command && !command.name);
 }
+
+const logLevelOrder: LogLevel[] = ['trace', 'debug', 'info', 'warn', 'error', 'fatal'];
+
+// Same as the filtering on the Rust side, lines without a level don't pass any filter
+export function isAtOrAboveLevel(level: string | null, minLevel: LogLevel | null) {
+  if (!minLevel) return true;
+  if (!level) return false;
+
+  return logLevelOrder.indexOf(level as LogLevel) >= logLevelOrder.indexOf(minLevel);
+}
+
+// Same order as the Rust side, by run and then arrival. A buffered multi-line entry can be stored
+// after lines that arrived later, so it's inserted in place instead of appended.
+export function mergeLogLines(current: CommandLogLine[], newLines: CommandLogLine[]) {
+  const merged = [...current];
+
+  for (const line of newLines) {
+    let index = merged.length;
+    while (index > 0 && compareLogLines(merged[index - 1], line) > 0) index--;
+    merged.splice(index, 0, line);
+  }
+
+  return merged;
+}
+
+function compareLogLines(a: CommandLogLine, b: CommandLogLine) {
+  return a.run - b.run || a.sequence - b.sequence || a.id - b.id;
+}
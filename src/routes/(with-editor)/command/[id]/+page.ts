@@ -1,4 +1,5 @@
 import { appAPI } from '$lib/api';
+import { getProcessStatusName } from '$lib/utils';
 import { error } from '@sveltejs/kit';
 
 export async function load({ params, depends }) {
@@ -7,13 +8,22 @@ export async function load({ params, depends }) {
   if (!command) {
     throw error(404, 'Command not found');
   }
-  const processStatus = await appAPI(depends).getProcessStatus(command.id);
+  const status = await appAPI(depends).getProcessStatus(command.id);
+  const processStatus = getProcessStatusName(status);
+  const instanceCount = typeof status === 'string' ? 0 : status.Running.instances;
 
-  const initialCommandLogLines = await appAPI(depends).getCommandLogLines(command.id);
+  const initialCommandLogLines = await appAPI(depends).getCommandLogLines(command.id, null);
+
+  const notificationRules = await appAPI(depends).getNotificationRules(command.id);
+
+  const triggers = await appAPI(depends).getTriggers(command.id);
 
   return {
     command,
     processStatus,
+    instanceCount,
     initialCommandLogLines,
+    notificationRules,
+    triggers,
   };
 }
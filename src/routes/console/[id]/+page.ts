@@ -0,0 +1,19 @@
+import { appAPI } from '$lib/api';
+import { WindowState } from '$lib/types';
+import { error } from '@sveltejs/kit';
+
+export async function load({ params, depends }) {
+  const command = await appAPI(depends).getCommand(parseInt(params.id));
+
+  if (!command) {
+    throw error(404, 'Command not found');
+  }
+
+  const initialCommandLogLines = await appAPI(depends).getCommandLogLines(command.id, null);
+
+  return {
+    windowState: WindowState.Console,
+    command,
+    initialCommandLogLines,
+  };
+}